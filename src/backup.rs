@@ -0,0 +1,196 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Backup file name: "<file name>.<UTC timestamp>.bak", for example "gs2.sav.20231001-120000.bak"
+const BACKUP_FILE_EXTENSION: &str = ".bak";
+
+/// Overwrite a save file safely:
+/// 1. Write the new data to a temp file in the same directory, and flush it to disk.
+/// 2. Keep the original file as a timestamped backup.
+/// 3. Replace the original file with the temp file by renaming, which is atomic on the same file system.
+/// 4. Remove old backups, only keep the newest `backup_count` ones.
+///
+/// Returns the location of the backup file.
+pub fn write_in_place(save_path: &Path, raw_save_file: &[u8], backup_count: usize) -> io::Result<PathBuf> {
+  let parent = match save_path.parent() {
+    Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+    _ => PathBuf::from("."),
+  };
+  let file_name = save_path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid save file location!"))?.to_string_lossy().to_string();
+
+  let temp_path = parent.join(format!(".{file_name}.tmp"));
+  let mut temp_file = File::create(&temp_path)?;
+  temp_file.write_all(raw_save_file)?;
+  temp_file.sync_all()?;
+  drop(temp_file);
+  // The new file replaces the original one, so it keeps the original permissions, like read-only for other users.
+  if let Err(error) = fs::metadata(save_path).and_then(|metadata| fs::set_permissions(&temp_path, metadata.permissions())) {
+    let _ = fs::remove_file(&temp_path);
+    return Err(error);
+  }
+
+  let backup_path = get_backup_path(&parent, &file_name);
+  // Hard link keeps the original file untouched until the rename below, fall back to copy if it is not supported.
+  if fs::hard_link(save_path, &backup_path).is_err() {
+    fs::copy(save_path, &backup_path)?;
+  }
+
+  if let Err(error) = fs::rename(&temp_path, save_path) {
+    let _ = fs::remove_file(&temp_path);
+    return Err(error);
+  }
+  // Make sure the rename itself is on disk, not supported on every platform.
+  if let Ok(dir) = File::open(&parent) {
+    let _ = dir.sync_all();
+  }
+
+  remove_old_backups(&parent, &file_name, backup_count)?;
+
+  Ok(backup_path)
+}
+
+fn get_backup_path(parent: &Path, file_name: &str) -> PathBuf {
+  let timestamp = get_utc_timestamp();
+  let mut backup_path = parent.join(format!("{file_name}.{timestamp}{BACKUP_FILE_EXTENSION}"));
+  // More than one backup in the same second.
+  let mut i = 1;
+  while backup_path.exists() {
+    backup_path = parent.join(format!("{file_name}.{timestamp}-{i}{BACKUP_FILE_EXTENSION}"));
+    i += 1;
+  }
+
+  backup_path
+}
+
+fn remove_old_backups(parent: &Path, file_name: &str, backup_count: usize) -> io::Result<()> {
+  let prefix = format!("{file_name}.");
+  let mut backup_files: Vec<((String, u32), String)> = fs::read_dir(parent)?
+    .filter_map(|entry| entry.ok())
+    .map(|entry| entry.file_name().to_string_lossy().to_string())
+    .filter(|name| name.starts_with(&prefix) && name.ends_with(BACKUP_FILE_EXTENSION))
+    .filter_map(|name| parse_timestamp(&name[prefix.len()..name.len() - BACKUP_FILE_EXTENSION.len()]).map(|timestamp| (timestamp, name.clone())))
+    .collect();
+  // The oldest ones come first, "-10" is newer than "-9" in the same second.
+  backup_files.sort();
+
+  let remove_count = backup_files.len().saturating_sub(backup_count);
+  for (_, backup_file_name) in &backup_files[..remove_count] {
+    fs::remove_file(parent.join(backup_file_name))?;
+  }
+
+  Ok(())
+}
+
+/// Parse "YYYYMMDD-hhmmss", optionally followed by "-<n>", to the timestamp and the counter, which is 0 if there is none.
+fn parse_timestamp(value: &str) -> Option<(String, u32)> {
+  let bytes = value.as_bytes();
+  if bytes.len() < 15 || !bytes[..8].iter().all(u8::is_ascii_digit) || bytes[8] != b'-' || !bytes[9..15].iter().all(u8::is_ascii_digit) {
+    return None;
+  }
+  let counter = match &value[15..] {
+    "" => 0,
+    counter_suffix => {
+      let counter_digits = counter_suffix.strip_prefix('-')?;
+      if counter_digits.is_empty() || !counter_digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+      }
+      counter_digits.parse().ok()?
+    }
+  };
+
+  Some((value[..15].to_string(), counter))
+}
+
+/// Current UTC time as "YYYYMMDD-hhmmss".
+fn get_utc_timestamp() -> String {
+  format_utc_timestamp(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs()))
+}
+
+/// Seconds since 1970-01-01 00:00:00 UTC as "YYYYMMDD-hhmmss".
+fn format_utc_timestamp(seconds: u64) -> String {
+  let days = seconds / 86400;
+  let seconds_of_day = seconds % 86400;
+
+  // Convert days since 1970-01-01 to a civil date, from Howard Hinnant's "days_from_civil" algorithms.
+  let z = days + 719_468;
+  let era = z / 146_097;
+  let day_of_era = z - era * 146_097;
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let mp = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+  format!("{year:04}{month:02}{day:02}-{:02}{:02}{:02}", seconds_of_day / 3600, seconds_of_day % 3600 / 60, seconds_of_day % 60)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_timestamp_with_and_without_counter() {
+    assert_eq!(parse_timestamp("20231001-120000"), Some((String::from("20231001-120000"), 0)));
+    assert_eq!(parse_timestamp("20231001-120000-12"), Some((String::from("20231001-120000"), 12)));
+    assert_eq!(parse_timestamp("20231001-120000-"), None);
+    assert_eq!(parse_timestamp("20231001-120000-1a"), None);
+    assert_eq!(parse_timestamp("20231001120000"), None);
+    assert_eq!(parse_timestamp("2023100a-120000"), None);
+  }
+
+  #[test]
+  fn format_utc_timestamps() {
+    assert_eq!(format_utc_timestamp(0), "19700101-000000");
+    assert_eq!(format_utc_timestamp(951_782_400), "20000229-000000");
+    assert_eq!(format_utc_timestamp(1_696_161_599), "20231001-115959");
+    assert_eq!(format_utc_timestamp(4_107_542_399), "21000228-235959");
+  }
+
+  #[test]
+  fn utc_timestamp_is_parsable() {
+    assert!(parse_timestamp(&get_utc_timestamp()).is_some());
+  }
+
+  #[test]
+  fn old_backups_are_removed_by_time_and_counter() {
+    let dir = std::env::temp_dir().join(format!("gs_backup_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let backup_names = ["gs.sav.20231001-120000.bak", "gs.sav.20231001-120000-2.bak", "gs.sav.20231001-120000-10.bak", "gs.sav.20230901-235959-11.bak"];
+    for backup_name in backup_names {
+      fs::write(dir.join(backup_name), b"").unwrap();
+    }
+    fs::write(dir.join("gs.sav.notes.bak"), b"").unwrap();
+
+    remove_old_backups(&dir, "gs.sav", 2).unwrap();
+    let mut kept_names: Vec<String> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().to_string()).collect();
+    kept_names.sort();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(kept_names, ["gs.sav.20231001-120000-10.bak", "gs.sav.20231001-120000-2.bak", "gs.sav.notes.bak"]);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn write_in_place_keeps_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("gs_backup_permissions_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let save_path = dir.join("gs.sav");
+    fs::write(&save_path, b"old").unwrap();
+    fs::set_permissions(&save_path, fs::Permissions::from_mode(0o640)).unwrap();
+
+    write_in_place(&save_path, b"new", 1).unwrap();
+    let mode = fs::metadata(&save_path).unwrap().permissions().mode() & 0o777;
+    let raw_save_file = fs::read(&save_path).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(mode, 0o640);
+    assert_eq!(raw_save_file, b"new");
+  }
+}
@@ -0,0 +1,343 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use crate::{convert_save_file, get_default_output_path, ConvertError, ConvertOptions};
+
+/// Files created by this tool itself, they are skipped when scanning directories and matching glob patterns.
+const IGNORED_FILE_EXTENSIONS: [&str; 2] = ["undo", "bak"];
+/// Default output files are named "<file name>_output.<extension>", see `get_default_output_path`.
+const IGNORED_FILE_STEM_SUFFIX: &str = "_output";
+
+/// One file to convert, `relative_path` is used to mirror the input tree in output directory.
+struct BatchJob {
+  input_path: PathBuf,
+  relative_path: PathBuf,
+  // The input is neither a file, a directory nor a glob pattern matching something, or another job writes the same output file.
+  error_option: Option<String>,
+  // A file created by this tool, found in a directory or by a glob pattern, it's listed as skipped.
+  ignored_reason_option: Option<String>,
+}
+
+enum BatchStatus {
+  Converted,
+  Skipped,
+  Failed,
+}
+
+struct BatchResult {
+  status: BatchStatus,
+  detail: String,
+  message: String,
+}
+
+/// Whether the input should be handled as batch conversion, directories and glob patterns are.
+pub fn is_batch_input(raw_input_path: &Path) -> bool {
+  raw_input_path.is_dir() || (!raw_input_path.exists() && is_glob_pattern(&raw_input_path.to_string_lossy()))
+}
+
+/// Convert all input files with same options, in parallel.
+/// One file that can't be converted doesn't stop the others, a summary table is printed at the end.
+pub fn run_batch(raw_input_paths: &[&PathBuf], output_dir_option: Option<&PathBuf>, recursive: bool, convert_options: &ConvertOptions) {
+  let mut batch_jobs = Vec::new();
+  for raw_input_path in raw_input_paths {
+    collect_batch_jobs(raw_input_path, recursive, &mut batch_jobs);
+  }
+  if batch_jobs.is_empty() {
+    eprintln!("There is no file to convert!");
+    return;
+  }
+  // Jobs run in parallel, two of them must never write the same file.
+  let output_paths: Vec<PathBuf> = batch_jobs.iter().map(|batch_job| get_output_path(batch_job, output_dir_option, convert_options)).collect();
+  mark_duplicate_output_paths(&mut batch_jobs, &output_paths);
+
+  let next_job_index = AtomicUsize::new(0);
+  let batch_results: Mutex<Vec<Option<BatchResult>>> = Mutex::new((0..batch_jobs.len()).map(|_| None).collect());
+  let thread_count = thread::available_parallelism().map_or(1, |count| count.get()).min(batch_jobs.len());
+  thread::scope(|scope| {
+    for _ in 0..thread_count {
+      scope.spawn(|| loop {
+        let job_index = next_job_index.fetch_add(1, Ordering::Relaxed);
+        let Some(batch_job) = batch_jobs.get(job_index) else {
+          break;
+        };
+        let batch_result = run_batch_job(batch_job, &output_paths[job_index], convert_options);
+        batch_results.lock().unwrap()[job_index] = Some(batch_result);
+      });
+    }
+  });
+  let batch_results: Vec<BatchResult> = batch_results.into_inner().unwrap().into_iter().map(Option::unwrap).collect();
+
+  // Messages like dry run results, in input order.
+  for (batch_job, batch_result) in batch_jobs.iter().zip(batch_results.iter()) {
+    if !batch_result.message.is_empty() {
+      println!("== {} ==", batch_job.input_path.to_string_lossy());
+      print!("{}", batch_result.message);
+    }
+  }
+
+  print_summary_table(&batch_jobs, &batch_results);
+}
+
+fn run_batch_job(batch_job: &BatchJob, output_path: &Path, convert_options: &ConvertOptions) -> BatchResult {
+  if let Some(error_message) = &batch_job.error_option {
+    return BatchResult { status: BatchStatus::Failed, detail: error_message.clone(), message: String::new() };
+  }
+  if let Some(ignored_reason) = &batch_job.ignored_reason_option {
+    return BatchResult { status: BatchStatus::Skipped, detail: ignored_reason.clone(), message: String::new() };
+  }
+
+  match convert_save_file(&batch_job.input_path, output_path, convert_options) {
+    Ok(message) => {
+      let detail = if convert_options.dry_run {
+        String::from("Dry run, nothing written")
+      } else {
+        format!("-> {}", output_path.to_string_lossy())
+      };
+      BatchResult { status: BatchStatus::Converted, detail, message }
+    }
+    Err(ConvertError::Skipped(error_message)) => BatchResult { status: BatchStatus::Skipped, detail: error_message, message: String::new() },
+    Err(ConvertError::Failed(error_message)) => BatchResult { status: BatchStatus::Failed, detail: error_message, message: String::new() },
+  }
+}
+
+fn get_output_path(batch_job: &BatchJob, output_dir_option: Option<&PathBuf>, convert_options: &ConvertOptions) -> PathBuf {
+  if convert_options.in_place_backup_count_option.is_some() {
+    batch_job.input_path.clone()
+  } else if let Some(output_dir) = output_dir_option {
+    let output_path = output_dir.join(&batch_job.relative_path);
+    match convert_options.wrap_format_option {
+      Some(wrap_format) => output_path.with_extension(wrap_format.file_extension()),
+      None => output_path,
+    }
+  } else {
+    get_default_output_path(&batch_job.input_path, "_output", convert_options.wrap_format_option)
+  }
+}
+
+/// Fail every job after the first one writing to the same output file,
+/// like "a/gs.sav" and "b/gs.sav" with "--output", or the same file given twice.
+fn mark_duplicate_output_paths(batch_jobs: &mut [BatchJob], output_paths: &[PathBuf]) {
+  // Skipped files and failed inputs don't write anything.
+  let is_written = |batch_job: &BatchJob| batch_job.error_option.is_none() && batch_job.ignored_reason_option.is_none();
+  for i in 0..batch_jobs.len() {
+    if !is_written(&batch_jobs[i]) {
+      continue;
+    }
+    if let Some(first_index) = (0..i).find(|j| is_written(&batch_jobs[*j]) && output_paths[*j] == output_paths[i]) {
+      let first_input_path = batch_jobs[first_index].input_path.to_string_lossy().to_string();
+      batch_jobs[i].error_option = Some(format!("Same output file \"{}\" as \"{first_input_path}\"!", output_paths[i].to_string_lossy()));
+    }
+  }
+}
+
+fn print_summary_table(batch_jobs: &[BatchJob], batch_results: &[BatchResult]) {
+  let file_column_width = batch_jobs.iter().map(|batch_job| batch_job.input_path.to_string_lossy().chars().count()).max().unwrap_or(0).max(4);
+  println!("{:<9}{:<width$}  Detail", "Status", "File", width = file_column_width);
+
+  let (mut converted_count, mut skipped_count, mut failed_count) = (0, 0, 0);
+  for (batch_job, batch_result) in batch_jobs.iter().zip(batch_results.iter()) {
+    let status = match batch_result.status {
+      BatchStatus::Converted => {
+        converted_count += 1;
+        "OK"
+      }
+      BatchStatus::Skipped => {
+        skipped_count += 1;
+        "SKIPPED"
+      }
+      BatchStatus::Failed => {
+        failed_count += 1;
+        "FAILED"
+      }
+    };
+    println!("{:<9}{:<width$}  {}", status, batch_job.input_path.to_string_lossy(), batch_result.detail, width = file_column_width);
+  }
+
+  println!("{} file(s): {converted_count} converted, {skipped_count} skipped, {failed_count} failed.", batch_jobs.len());
+}
+
+fn collect_batch_jobs(raw_input_path: &Path, recursive: bool, batch_jobs: &mut Vec<BatchJob>) {
+  if raw_input_path.is_file() {
+    batch_jobs.push(BatchJob { input_path: raw_input_path.to_path_buf(), relative_path: PathBuf::from(raw_input_path.file_name().unwrap()), error_option: None, ignored_reason_option: None });
+  } else if raw_input_path.is_dir() {
+    let mut file_paths = Vec::new();
+    walk_dir(raw_input_path, recursive, &mut file_paths);
+    for file_path in file_paths {
+      let relative_path = file_path.strip_prefix(raw_input_path).unwrap().to_path_buf();
+      let ignored_reason_option = get_ignored_reason(&file_path);
+      batch_jobs.push(BatchJob { input_path: file_path, relative_path, error_option: None, ignored_reason_option });
+    }
+  } else if is_glob_pattern(&raw_input_path.to_string_lossy()) {
+    let (base_dir, pattern_components) = split_glob_pattern(raw_input_path);
+    let mut file_paths = Vec::new();
+    walk_dir(&base_dir, true, &mut file_paths);
+    let mut matched = false;
+    for file_path in file_paths {
+      let relative_path = file_path.strip_prefix(&base_dir).unwrap().to_path_buf();
+      let relative_components: Vec<String> = relative_path.components().map(|component| component.as_os_str().to_string_lossy().to_string()).collect();
+      if match_path_components(&pattern_components, &relative_components) {
+        matched = true;
+        let ignored_reason_option = get_ignored_reason(&file_path);
+        batch_jobs.push(BatchJob { input_path: file_path, relative_path, error_option: None, ignored_reason_option });
+      }
+    }
+    if !matched {
+      batch_jobs.push(BatchJob { input_path: raw_input_path.to_path_buf(), relative_path: PathBuf::new(), error_option: Some(String::from("No file matches this pattern!")), ignored_reason_option: None });
+    }
+  } else {
+    batch_jobs.push(BatchJob { input_path: raw_input_path.to_path_buf(), relative_path: PathBuf::new(), error_option: Some(String::from("File not found!")), ignored_reason_option: None });
+  }
+}
+
+/// Why a file is skipped, undo files, backups and default output files of earlier runs are.
+/// A file given by its own path is never skipped, so a save file with such a name can still be converted.
+fn get_ignored_reason(file_path: &Path) -> Option<String> {
+  if let Some(extension) = file_path.extension().filter(|extension| IGNORED_FILE_EXTENSIONS.contains(&extension.to_string_lossy().as_ref())) {
+    return Some(format!("Undo file or backup (\"*.{}\") of this tool, give its path to convert it", extension.to_string_lossy()));
+  }
+  file_path.file_stem().filter(|file_stem| file_stem.to_string_lossy().ends_with(IGNORED_FILE_STEM_SUFFIX))
+    .map(|_| format!("Output file of an earlier run (\"*{IGNORED_FILE_STEM_SUFFIX}.*\"), give its path to convert it"))
+}
+
+/// Get all files in a directory, sorted by path.
+fn walk_dir(dir: &Path, recursive: bool, file_paths: &mut Vec<PathBuf>) {
+  let Ok(read_dir) = fs::read_dir(dir) else {
+    return;
+  };
+  let mut entry_paths: Vec<PathBuf> = read_dir.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+  entry_paths.sort();
+  for entry_path in entry_paths {
+    if entry_path.is_dir() {
+      if recursive {
+        walk_dir(&entry_path, recursive, file_paths);
+      }
+    } else {
+      file_paths.push(entry_path);
+    }
+  }
+}
+
+fn is_glob_pattern(value: &str) -> bool {
+  value.contains(['*', '?'])
+}
+
+/// Split a glob pattern into the directory before the first wildcard and the remaining components.
+/// "saves/**/gs?.sav" -> ("saves", ["**", "gs?.sav"])
+fn split_glob_pattern(pattern: &Path) -> (PathBuf, Vec<String>) {
+  let mut base_dir = PathBuf::new();
+  let mut pattern_components = Vec::new();
+  for component in pattern.components() {
+    let component_str = component.as_os_str().to_string_lossy().to_string();
+    if pattern_components.is_empty() && !is_glob_pattern(&component_str) {
+      base_dir.push(component.as_os_str());
+    } else if !matches!(component, Component::CurDir) {
+      pattern_components.push(component_str);
+    }
+  }
+  if base_dir.as_os_str().is_empty() {
+    base_dir.push(".");
+  }
+
+  (base_dir, pattern_components)
+}
+
+/// "**" matches any number of directories, other components are matched by `match_wildcard`.
+fn match_path_components(pattern_components: &[String], path_components: &[String]) -> bool {
+  match pattern_components.first() {
+    None => path_components.is_empty(),
+    Some(pattern_component) if pattern_component == "**" => {
+      (0..=path_components.len()).any(|i| match_path_components(&pattern_components[1..], &path_components[i..]))
+    }
+    Some(pattern_component) => {
+      !path_components.is_empty() && match_wildcard(pattern_component.as_bytes(), path_components[0].as_bytes()) && match_path_components(&pattern_components[1..], &path_components[1..])
+    }
+  }
+}
+
+/// "*" matches any number of characters, "?" matches one character.
+fn match_wildcard(pattern: &[u8], text: &[u8]) -> bool {
+  match pattern.first() {
+    None => text.is_empty(),
+    Some(b'*') => (0..=text.len()).any(|i| match_wildcard(&pattern[1..], &text[i..])),
+    Some(b'?') => !text.is_empty() && match_wildcard(&pattern[1..], &text[1..]),
+    Some(byte) => text.first() == Some(byte) && match_wildcard(&pattern[1..], &text[1..]),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn create_batch_job(input_path: &str, relative_path: &str) -> BatchJob {
+    BatchJob { input_path: PathBuf::from(input_path), relative_path: PathBuf::from(relative_path), error_option: None, ignored_reason_option: None }
+  }
+
+  #[test]
+  fn duplicate_output_paths_fail_after_the_first_job() {
+    let mut batch_jobs = vec![create_batch_job("a/gs.sav", "gs.sav"), create_batch_job("b/gs.sav", "gs.sav"), create_batch_job("b/gs2.sav", "gs2.sav"), create_batch_job("a/gs.sav", "gs.sav")];
+    let output_paths: Vec<PathBuf> = ["out/gs.sav", "out/gs.sav", "out/gs2.sav", "out/gs.sav"].iter().map(PathBuf::from).collect();
+    mark_duplicate_output_paths(&mut batch_jobs, &output_paths);
+
+    assert!(batch_jobs[0].error_option.is_none());
+    assert!(batch_jobs[1].error_option.as_ref().is_some_and(|error_message| error_message.contains("\"a/gs.sav\"")));
+    assert!(batch_jobs[2].error_option.is_none());
+    assert!(batch_jobs[3].error_option.is_some());
+  }
+
+  #[test]
+  fn skipped_files_have_no_output_file() {
+    let mut batch_jobs = vec![create_batch_job("a/gs_output.sav", "gs_output.sav"), create_batch_job("b/gs_output.sav", "gs_output.sav")];
+    batch_jobs[0].ignored_reason_option = get_ignored_reason(&batch_jobs[0].input_path);
+    let output_paths: Vec<PathBuf> = ["out/gs_output.sav", "out/gs_output.sav"].iter().map(PathBuf::from).collect();
+    mark_duplicate_output_paths(&mut batch_jobs, &output_paths);
+
+    assert!(batch_jobs[1].error_option.is_none());
+  }
+
+  #[test]
+  fn files_created_by_this_tool_are_ignored() {
+    assert_eq!(get_ignored_reason(Path::new("saves/gs.sav.undo")).as_deref(), Some("Undo file or backup (\"*.undo\") of this tool, give its path to convert it"));
+    assert!(get_ignored_reason(Path::new("saves/gs.sav.20231001-120000.bak")).is_some());
+    assert_eq!(get_ignored_reason(Path::new("saves/gs_output.sav")).as_deref(), Some("Output file of an earlier run (\"*_output.*\"), give its path to convert it"));
+    assert!(get_ignored_reason(Path::new("saves/gs_output")).is_some());
+    assert!(get_ignored_reason(Path::new("saves/gs.sav")).is_none());
+    assert!(get_ignored_reason(Path::new("saves/output.sav")).is_none());
+  }
+
+  fn match_glob(pattern: &str, path: &str) -> bool {
+    let (_, pattern_components) = split_glob_pattern(Path::new(pattern));
+    let path_components: Vec<String> = path.split('/').map(String::from).collect();
+    match_path_components(&pattern_components, &path_components)
+  }
+
+  #[test]
+  fn split_glob_pattern_at_first_wildcard() {
+    assert_eq!(split_glob_pattern(Path::new("saves/**/gs?.sav")), (PathBuf::from("saves"), vec![String::from("**"), String::from("gs?.sav")]));
+    assert_eq!(split_glob_pattern(Path::new("*.sav")), (PathBuf::from("."), vec![String::from("*.sav")]));
+    assert_eq!(split_glob_pattern(Path::new("a/b/*/c.sav")), (PathBuf::from("a/b"), vec![String::from("*"), String::from("c.sav")]));
+  }
+
+  #[test]
+  fn match_wildcards() {
+    assert!(match_wildcard(b"*.sav", b"gs.sav"));
+    assert!(match_wildcard(b"*.sav", b".sav"));
+    assert!(match_wildcard(b"gs?.sav", b"gs2.sav"));
+    assert!(!match_wildcard(b"gs?.sav", b"gs.sav"));
+    assert!(!match_wildcard(b"*.sav", b"gs.sav.bak"));
+    assert!(match_wildcard(b"*a*b*", b"xxaxxbxx"));
+    assert!(!match_wildcard(b"*a*b*", b"xxbxxaxx"));
+  }
+
+  #[test]
+  fn match_double_star_directories() {
+    assert!(match_glob("saves/**/*.sav", "gs.sav"));
+    assert!(match_glob("saves/**/*.sav", "a/b/gs.sav"));
+    assert!(!match_glob("saves/**/*.sav", "a/b/gs.srm"));
+    assert!(match_glob("saves/*/*.sav", "a/gs.sav"));
+    assert!(!match_glob("saves/*/*.sav", "gs.sav"));
+    assert!(!match_glob("saves/*/*.sav", "a/b/gs.sav"));
+    assert!(match_glob("saves/**", "a/gs.sav"));
+  }
+}
@@ -0,0 +1,135 @@
+use crate::field::{decode_name, PC_DATA_SIZE, PC_LABEL, PC_NAME_SIZE};
+use crate::{PARTY_MEMBERS_COUNT, PC_NAME_LOCATION_INDEX};
+
+/// Elements in the order used by character data.
+pub const ELEMENT_LABEL: [&str; 4] = ["Venus", "Mercury", "Mars", "Jupiter"];
+
+/* Playable character data, 0x14C bytes for each character, starts with the name.
+   Source: Golden Sun Hacking Community RAM map, same layout in TBS and TLA.
+
+   0x00   Name (15 bytes)
+   0x0F   Level
+   0x10   Base max HP, base max PP (u16)
+   0x18   Base attack, base defense, base agility (u16), base luck (u8)
+   0x24   Base elemental levels (u8, Venus, Mercury, Mars, Jupiter)
+   0x34   Max HP, max PP, current HP, current PP (u16)
+   0x3C   Attack, defense, agility (u16), luck (u8)
+   0x48   Elemental levels (u8, Venus, Mercury, Mars, Jupiter)
+   0x58   Psynergy (32 u32 entries, see psynergy.rs)
+   0xD8   Items (15 u16 slots, see inventory.rs)
+   0x124  Experience (u32)
+   0x129  Class
+
+   Base values come from level and class, the others include equipment and djinn.
+   The game recalculates them when the character's equipment or djinn change. */
+pub struct CharacterField {
+  // Used in "edit --set <character>.<key>=<value>".
+  pub key: &'static str,
+  pub label: &'static str,
+  pub offset: usize,
+  pub size: usize,
+  pub min_value: u32,
+  // The limit of the game, larger values can't be shown in status screen.
+  pub max_value: u32,
+}
+
+pub const CHARACTER_FIELDS: [CharacterField; 24] = [
+  CharacterField { key: "level", label: "Level", offset: 0x0F, size: 1, min_value: 1, max_value: 99 },
+  CharacterField { key: "exp", label: "Experience", offset: 0x124, size: 4, min_value: 0, max_value: 9_999_999 },
+  CharacterField { key: "hp", label: "HP", offset: 0x38, size: 2, min_value: 0, max_value: 9999 },
+  CharacterField { key: "max_hp", label: "Max HP", offset: 0x34, size: 2, min_value: 1, max_value: 9999 },
+  CharacterField { key: "pp", label: "PP", offset: 0x3A, size: 2, min_value: 0, max_value: 9999 },
+  CharacterField { key: "max_pp", label: "Max PP", offset: 0x36, size: 2, min_value: 0, max_value: 9999 },
+  CharacterField { key: "attack", label: "Attack", offset: 0x3C, size: 2, min_value: 0, max_value: 9999 },
+  CharacterField { key: "defense", label: "Defense", offset: 0x3E, size: 2, min_value: 0, max_value: 9999 },
+  CharacterField { key: "agility", label: "Agility", offset: 0x40, size: 2, min_value: 0, max_value: 9999 },
+  CharacterField { key: "luck", label: "Luck", offset: 0x42, size: 1, min_value: 0, max_value: 99 },
+  CharacterField { key: "venus_level", label: "Venus level", offset: 0x48, size: 1, min_value: 0, max_value: 99 },
+  CharacterField { key: "mercury_level", label: "Mercury level", offset: 0x49, size: 1, min_value: 0, max_value: 99 },
+  CharacterField { key: "mars_level", label: "Mars level", offset: 0x4A, size: 1, min_value: 0, max_value: 99 },
+  CharacterField { key: "jupiter_level", label: "Jupiter level", offset: 0x4B, size: 1, min_value: 0, max_value: 99 },
+  CharacterField { key: "base_hp", label: "Base max HP", offset: 0x10, size: 2, min_value: 1, max_value: 9999 },
+  CharacterField { key: "base_pp", label: "Base max PP", offset: 0x12, size: 2, min_value: 0, max_value: 9999 },
+  CharacterField { key: "base_attack", label: "Base attack", offset: 0x18, size: 2, min_value: 0, max_value: 9999 },
+  CharacterField { key: "base_defense", label: "Base defense", offset: 0x1A, size: 2, min_value: 0, max_value: 9999 },
+  CharacterField { key: "base_agility", label: "Base agility", offset: 0x1C, size: 2, min_value: 0, max_value: 9999 },
+  CharacterField { key: "base_luck", label: "Base luck", offset: 0x1E, size: 1, min_value: 0, max_value: 99 },
+  CharacterField { key: "base_venus_level", label: "Base Venus level", offset: 0x24, size: 1, min_value: 0, max_value: 99 },
+  CharacterField { key: "base_mercury_level", label: "Base Mercury level", offset: 0x25, size: 1, min_value: 0, max_value: 99 },
+  CharacterField { key: "base_mars_level", label: "Base Mars level", offset: 0x26, size: 1, min_value: 0, max_value: 99 },
+  CharacterField { key: "base_jupiter_level", label: "Base Jupiter level", offset: 0x27, size: 1, min_value: 0, max_value: 99 },
+];
+
+/// Other known parts of character data (offset, size, label), only used as labels in output.
+const CHARACTER_DATA_RANGE_LABEL: [(usize, usize, &str); 7] = [
+  (0x58, 0x80, "Psynergy"),
+  (0xD8, 0x1E, "Items"),
+  (0xF8, 0x10, "Djinn owned"),
+  (0x108, 0x10, "Djinn set"),
+  (0x118, 4, "Djinn owned count"),
+  (0x11C, 4, "Djinn set count"),
+  (0x129, 1, "Class"),
+];
+
+/// Current HP/PP can't be more than max HP/PP.
+const CURRENT_MAX_FIELD_KEYS: [(&str, &str); 2] = [("hp", "max_hp"), ("pp", "max_pp")];
+
+/// The start of a character's data in a save data block.
+pub fn get_character_offset(pc_index: usize, game_type_index: usize) -> usize {
+  PC_NAME_LOCATION_INDEX[game_type_index] + pc_index * PC_DATA_SIZE
+}
+
+/// Find a character by its English name, case insensitive.
+pub fn find_character(character_name: &str, game_type_index: usize) -> Option<usize> {
+  PC_LABEL.iter().take(PARTY_MEMBERS_COUNT[game_type_index]).position(|pc_label| pc_label.eq_ignore_ascii_case(character_name))
+}
+
+pub fn find_character_field(key: &str) -> Option<&'static CharacterField> {
+  CHARACTER_FIELDS.iter().find(|character_field| character_field.key.eq_ignore_ascii_case(key))
+}
+
+pub fn read_character_field(raw_block: &[u8], pc_index: usize, character_field: &CharacterField, game_type_index: usize) -> u32 {
+  let location_index = get_character_offset(pc_index, game_type_index) + character_field.offset;
+  raw_block[location_index..location_index + character_field.size].iter().rev().fold(0u32, |value, byte| (value << 8) | u32::from(*byte))
+}
+
+/// Write a field after checking the game's limits, current HP/PP can't be more than max HP/PP.
+pub fn write_character_field(raw_block: &mut [u8], pc_index: usize, character_field: &CharacterField, value: u32, game_type_index: usize) -> Result<(), String> {
+  if value < character_field.min_value || value > character_field.max_value {
+    return Err(format!("{} must be between {} and {}!", character_field.label, character_field.min_value, character_field.max_value));
+  }
+  for (current_key, max_key) in CURRENT_MAX_FIELD_KEYS {
+    if character_field.key == current_key {
+      let max_field = find_character_field(max_key).unwrap();
+      let max_value = read_character_field(raw_block, pc_index, max_field, game_type_index);
+      if value > max_value {
+        return Err(format!("{} can't be more than {} ({max_value})!", character_field.label, max_field.label));
+      }
+    } else if character_field.key == max_key {
+      let current_field = find_character_field(current_key).unwrap();
+      let current_value = read_character_field(raw_block, pc_index, current_field, game_type_index);
+      if value < current_value {
+        return Err(format!("{} can't be less than {} ({current_value}), change {} first!", character_field.label, current_field.label, current_field.key));
+      }
+    }
+  }
+
+  let location_index = get_character_offset(pc_index, game_type_index) + character_field.offset;
+  raw_block[location_index..location_index + character_field.size].copy_from_slice(&value.to_le_bytes()[..character_field.size]);
+
+  Ok(())
+}
+
+pub fn get_character_name(raw_block: &[u8], pc_index: usize, game_type_index: usize) -> String {
+  let location_index = get_character_offset(pc_index, game_type_index);
+  decode_name(&raw_block[location_index..location_index + PC_NAME_SIZE])
+}
+
+/// Describe which part of character data an offset belongs to, like "Level".
+pub fn get_character_data_label(pc_data_offset: usize) -> Option<&'static str> {
+  if let Some(character_field) = CHARACTER_FIELDS.iter().find(|character_field| (character_field.offset..character_field.offset + character_field.size).contains(&pc_data_offset)) {
+    return Some(character_field.label);
+  }
+
+  CHARACTER_DATA_RANGE_LABEL.iter().find(|(offset, size, _)| (*offset..*offset + *size).contains(&pc_data_offset)).map(|(_, _, label)| *label)
+}
@@ -0,0 +1,109 @@
+use crate::character::{find_character_field, get_character_offset, read_character_field, ELEMENT_LABEL};
+use crate::djinn::get_set_djinn_counts;
+use crate::field::PC_LABEL;
+use crate::gamedata::{GameData, CLASS_DATA_FILE_NAME};
+use crate::inventory::get_equipped_item_ids;
+
+/* A character's class comes from their base element and their elemental levels,
+   the elemental levels are the base elemental levels plus 1 for each set djinni of that element.
+   The game checks its class table in order and keeps the last class whose requirements are met,
+   so classes.txt must be in the order of class IDs.
+   Items like Mysterious Card change the class when equipped, the class of a character with one of them is not computed. */
+const CLASS_OFFSET: usize = 0x129;
+const PC_BASE_ELEMENT_INDEX: [usize; 8] = [0, 2, 3, 1, 0, 2, 3, 1];
+
+const BASE_ELEMENTAL_LEVEL_FIELD_KEYS: [&str; 4] = ["base_venus_level", "base_mercury_level", "base_mars_level", "base_jupiter_level"];
+
+pub fn get_character_class_id(raw_block: &[u8], pc_index: usize, game_type_index: usize) -> u8 {
+  raw_block[get_character_offset(pc_index, game_type_index) + CLASS_OFFSET]
+}
+
+pub fn set_character_class_id(raw_block: &mut [u8], pc_index: usize, class_id: u8, game_type_index: usize) {
+  raw_block[get_character_offset(pc_index, game_type_index) + CLASS_OFFSET] = class_id;
+}
+
+/// Elemental levels with set djinn (Venus, Mercury, Mars, Jupiter).
+pub fn compute_elemental_levels(raw_block: &[u8], pc_index: usize, game_type_index: usize) -> [u32; 4] {
+  let set_djinn_counts = get_set_djinn_counts(raw_block, pc_index, game_type_index);
+  std::array::from_fn(|element_index| {
+    let base_level = read_character_field(raw_block, pc_index, find_character_field(BASE_ELEMENTAL_LEVEL_FIELD_KEYS[element_index]).unwrap(), game_type_index);
+    base_level + u32::from(set_djinn_counts[element_index])
+  })
+}
+
+/// The class the game would give a character, the error tells why it can't be computed with the class data.
+/// No class data is built in, so the error should be shown.
+pub fn compute_class_id(raw_block: &[u8], pc_index: usize, game_type_index: usize, game_data: &GameData) -> Result<u8, String> {
+  if game_data.classes.is_empty() {
+    return Err(format!("Classes are not computed, {CLASS_DATA_FILE_NAME} is not in data directory (see \"--data\")."));
+  }
+  let has_class_item = get_equipped_item_ids(raw_block, pc_index, game_type_index).iter().any(|item_id| game_data.find_item(*item_id).is_some_and(|item_data| item_data.is_class_item));
+  if has_class_item {
+    return Err(format!("{}'s class is not computed, an item that changes the class is equipped.", PC_LABEL[pc_index]));
+  }
+
+  let elemental_levels = compute_elemental_levels(raw_block, pc_index, game_type_index);
+  game_data.classes.iter().rev()
+    .find(|class_data| {
+      class_data.base_element_indexes.contains(&PC_BASE_ELEMENT_INDEX[pc_index])
+        && class_data.required_levels_option.is_some_and(|required_levels| required_levels.iter().zip(elemental_levels).all(|(required_level, level)| u32::from(*required_level) <= level))
+    })
+    .map(|class_data| class_data.id)
+    .ok_or_else(|| format!(
+      "{}'s class is not computed, no class in {CLASS_DATA_FILE_NAME} is for {} adepts with elemental levels {}.",
+      PC_LABEL[pc_index], ELEMENT_LABEL[PC_BASE_ELEMENT_INDEX[pc_index]], elemental_levels.map(|level| level.to_string()).join("/")
+    ))
+}
+
+/// Like "Squire (stored), Guard (computed)".
+pub fn describe_character_class(raw_block: &[u8], pc_index: usize, game_type_index: usize, game_data: &GameData) -> String {
+  let stored_class_name = game_data.get_class_name(get_character_class_id(raw_block, pc_index, game_type_index));
+  let elemental_levels = compute_elemental_levels(raw_block, pc_index, game_type_index);
+  let elemental_level_description = elemental_levels.map(|level| level.to_string()).join("/");
+
+  match compute_class_id(raw_block, pc_index, game_type_index, game_data) {
+    Ok(class_id) => format!("{stored_class_name} (stored), {} (computed, {} adept, elemental levels {elemental_level_description})", game_data.get_class_name(class_id), ELEMENT_LABEL[PC_BASE_ELEMENT_INDEX[pc_index]]),
+    Err(_) => format!("{stored_class_name} (stored)"),
+  }
+}
+
+/// Warn if the stored class is not the class the game would compute.
+pub fn check_character_class(raw_block: &[u8], pc_index: usize, game_type_index: usize, game_data: &GameData) -> Option<String> {
+  let stored_class_id = get_character_class_id(raw_block, pc_index, game_type_index);
+  let computed_class_id = compute_class_id(raw_block, pc_index, game_type_index, game_data).ok()?;
+
+  (stored_class_id != computed_class_id).then(|| format!("{}'s class is {}, but the game would compute {}.", PC_LABEL[pc_index], game_data.get_class_name(stored_class_id), game_data.get_class_name(computed_class_id)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::character::write_character_field;
+  use crate::gamedata::ClassData;
+  use crate::SAVE_SLOT_SIZE;
+
+  fn create_class_data(id: u8, name: &str, required_levels: [u8; 4]) -> ClassData {
+    ClassData { id, name: String::from(name), psynergy_levels: Vec::new(), required_levels_option: Some(required_levels), base_element_indexes: vec![0], stat_multipliers_option: None }
+  }
+
+  #[test]
+  fn missing_class_data_is_reported() {
+    let raw_block = vec![0u8; SAVE_SLOT_SIZE[1]];
+    assert_eq!(compute_class_id(&raw_block, 0, 1, &GameData::default()), Err(String::from("Classes are not computed, classes.txt is not in data directory (see \"--data\").")));
+
+    let game_data = GameData { classes: vec![create_class_data(0x00, "Squire", [0, 0, 0, 0])], ..GameData::default() };
+    assert_eq!(compute_class_id(&raw_block, 0, 1, &game_data), Ok(0x00));
+    assert_eq!(compute_class_id(&raw_block, 1, 1, &game_data), Err(String::from("Garet's class is not computed, no class in classes.txt is for Mars adepts with elemental levels 0/0/0/0.")));
+  }
+
+  #[test]
+  fn last_class_with_requirements_met_is_chosen() {
+    let mut raw_block = vec![0u8; SAVE_SLOT_SIZE[1]];
+    let game_data = GameData { classes: vec![create_class_data(0x00, "Squire", [0, 0, 0, 0]), create_class_data(0x01, "Knight", [3, 0, 0, 0])], ..GameData::default() };
+    assert_eq!(check_character_class(&raw_block, 0, 1, &game_data), None);
+
+    write_character_field(&mut raw_block, 0, find_character_field("base_venus_level").unwrap(), 3, 1).unwrap();
+    assert_eq!(compute_class_id(&raw_block, 0, 1, &game_data), Ok(0x01));
+    assert_eq!(check_character_class(&raw_block, 0, 1, &game_data), Some(String::from("Isaac's class is Squire, but the game would compute Knight.")));
+  }
+}
@@ -0,0 +1,177 @@
+use clap::ValueEnum;
+use clap::builder::PossibleValue;
+
+mod embedded;
+mod gsv;
+mod mgba;
+mod nocash;
+mod sharkport;
+mod swap;
+mod trailing;
+mod zlib;
+
+/// Game title and game code in GBA ROM header, used by save file formats of PC link software.
+const ROM_GAME_TITLE_LOCATION_INDEX: usize = 0xA0;
+/// Fixed value 0x96 in GBA ROM header.
+const ROM_FIXED_VALUE_LOCATION_INDEX: usize = 0xB2;
+pub const ROM_HEADER_SIZE: usize = 0xC0;
+
+/// The format a flash image is stored in.
+pub enum SaveContainer {
+  // Plain flash image, used by most emulators and flashcarts.
+  Raw,
+  NoCashGba(nocash::NoCashGbaHeader),
+  MgbaState(mgba::MgbaState),
+  SharkPort(sharkport::SharkPortHeader),
+  GameSharkSp(gsv::GsvHeader),
+  // Flash image with unknown data around it, found by its save blocks.
+  Embedded(embedded::EmbeddedImage),
+  // Flash image with footer or padding after it.
+  Trailing(trailing::TrailingData),
+  // Flash image with the bytes of every 16-bit or 32-bit word swapped.
+  ByteSwapped(swap::ByteSwap),
+}
+
+/// Save file formats that can be created from a flash image, with the header of game ROM.
+#[derive(Clone, Copy)]
+pub enum WrapFormat {
+  SharkPort,
+  GameSharkSp,
+}
+
+impl WrapFormat {
+  pub fn file_extension(self) -> &'static str {
+    match self {
+      Self::SharkPort => "sps",
+      Self::GameSharkSp => "gsv",
+    }
+  }
+}
+
+impl ValueEnum for WrapFormat {
+  fn value_variants<'a>() -> &'a [Self] {
+    &[Self::SharkPort, Self::GameSharkSp]
+  }
+
+  fn to_possible_value(&self) -> Option<PossibleValue> {
+    Some(match self {
+      Self::SharkPort => PossibleValue::new("sps").help("SharkPort save file (.sps/.xps), Action Replay/GameShark PC link software"),
+      Self::GameSharkSp => PossibleValue::new("gsv").help("GameShark SP save file (.gsv)"),
+    })
+  }
+}
+
+impl SaveContainer {
+  pub fn description(&self) -> &'static str {
+    match self {
+      Self::Raw => "raw flash image",
+      Self::NoCashGba(_) => "No$GBA save file",
+      Self::MgbaState(_) => "mGBA save state",
+      Self::SharkPort(_) => "SharkPort save file",
+      Self::GameSharkSp(_) => "GameShark SP save file",
+      Self::Embedded(_) => "flash image embedded in another file",
+      Self::Trailing(trailing_data) => match trailing_data.kind {
+        trailing::TrailingKind::RtcFooter => "flash image with RTC footer",
+        trailing::TrailingKind::Padding => "flash image with padding",
+        trailing::TrailingKind::Unknown => "flash image with unknown trailing data",
+      },
+      Self::ByteSwapped(swap::ByteSwap::Swap16) => "flash image with every 16-bit word byte-swapped",
+      Self::ByteSwapped(swap::ByteSwap::Swap32) => "flash image with every 32-bit word byte-swapped",
+    }
+  }
+
+  /// Put a (converted) flash image back into the same container format.
+  pub fn wrap(&self, raw_save_file: &[u8]) -> Vec<u8> {
+    match self {
+      Self::Raw => raw_save_file.to_vec(),
+      Self::NoCashGba(header) => nocash::wrap(header, raw_save_file),
+      Self::MgbaState(mgba_state) => mgba::wrap(mgba_state, raw_save_file),
+      Self::SharkPort(header) => sharkport::wrap(header, raw_save_file),
+      Self::GameSharkSp(header) => gsv::wrap(header, raw_save_file),
+      Self::Embedded(embedded_image) => embedded::wrap(embedded_image, raw_save_file),
+      Self::Trailing(trailing_data) => trailing::wrap(trailing_data, raw_save_file),
+      Self::ByteSwapped(byte_swap) => swap::swap_bytes(raw_save_file, *byte_swap),
+    }
+  }
+
+  /// Whether the size of flash image in this container can't be changed.
+  pub fn is_fixed_size(&self) -> bool {
+    match self {
+      Self::MgbaState(mgba_state) => mgba_state.is_fixed_size(),
+      Self::Embedded(_) => true,
+      _ => false,
+    }
+  }
+
+  /// The size of unknown data after the flash image, it's only accepted with "--ignore-trailing".
+  pub fn get_unknown_trailing_size_option(&self) -> Option<usize> {
+    match self {
+      Self::Trailing(trailing_data) if matches!(trailing_data.kind, trailing::TrailingKind::Unknown) => Some(trailing_data.size()),
+      _ => None,
+    }
+  }
+
+  pub fn is_wrap_format(&self, wrap_format: WrapFormat) -> bool {
+    matches!((self, wrap_format), (Self::SharkPort(_), WrapFormat::SharkPort) | (Self::GameSharkSp(_), WrapFormat::GameSharkSp))
+  }
+}
+
+/// Detect the container format of a save file, and get the flash image from it.
+pub fn unwrap_save_file(file_bytes: Vec<u8>) -> Result<(Vec<u8>, SaveContainer), String> {
+  if nocash::is_nocash_gba_save(&file_bytes) {
+    let (header, raw_save_file) = nocash::unwrap(&file_bytes)?;
+    return Ok((raw_save_file, SaveContainer::NoCashGba(header)));
+  }
+  if mgba::is_mgba_state(&file_bytes) {
+    let (mgba_state, raw_save_file) = mgba::unwrap(file_bytes)?;
+    return Ok((raw_save_file, SaveContainer::MgbaState(mgba_state)));
+  }
+
+  if sharkport::is_sharkport_save(&file_bytes) {
+    let (header, raw_save_file) = sharkport::unwrap(&file_bytes)?;
+    return Ok((raw_save_file, SaveContainer::SharkPort(header)));
+  }
+  if gsv::is_gsv_save(&file_bytes) {
+    let (header, raw_save_file) = gsv::unwrap(&file_bytes)?;
+    return Ok((raw_save_file, SaveContainer::GameSharkSp(header)));
+  }
+
+  // Plain flash images are 64KB or 128KB.
+  if file_bytes.len() != 0x10000 && file_bytes.len() != 0x20000 {
+    if let Some((kind, image_size)) = trailing::find_known_trailing_data(&file_bytes) {
+      let (trailing_data, raw_save_file) = trailing::unwrap(&file_bytes, kind, image_size);
+      return Ok((raw_save_file, SaveContainer::Trailing(trailing_data)));
+    }
+    if let Some(start) = embedded::find_flash_image(&file_bytes)? {
+      let (embedded_image, raw_save_file) = embedded::unwrap(&file_bytes, start);
+      return Ok((raw_save_file, SaveContainer::Embedded(embedded_image)));
+    }
+    if let Some((kind, image_size)) = trailing::find_unknown_trailing_data(&file_bytes) {
+      let (trailing_data, raw_save_file) = trailing::unwrap(&file_bytes, kind, image_size);
+      return Ok((raw_save_file, SaveContainer::Trailing(trailing_data)));
+    }
+  }
+
+  if let Some(byte_swap) = swap::find_byte_swap(&file_bytes) {
+    return Ok((swap::swap_bytes(&file_bytes, byte_swap), SaveContainer::ByteSwapped(byte_swap)));
+  }
+
+  Ok((file_bytes, SaveContainer::Raw))
+}
+
+/// Create a new container with the game information from the header of game ROM (the first 0xC0 bytes).
+pub fn create_container(wrap_format: WrapFormat, rom_header: &[u8]) -> SaveContainer {
+  match wrap_format {
+    WrapFormat::SharkPort => SaveContainer::SharkPort(sharkport::create_header(rom_header)),
+    WrapFormat::GameSharkSp => SaveContainer::GameSharkSp(gsv::create_header(rom_header)),
+  }
+}
+
+/// Simple check for GBA ROM header.
+pub fn is_gba_rom_header(rom_header: &[u8]) -> bool {
+  rom_header.len() >= ROM_HEADER_SIZE && rom_header[ROM_FIXED_VALUE_LOCATION_INDEX] == 0x96
+}
+
+fn read_u32(bytes: &[u8], location_index: usize) -> u32 {
+  u32::from_le_bytes([bytes[location_index], bytes[location_index + 1], bytes[location_index + 2], bytes[location_index + 3]])
+}
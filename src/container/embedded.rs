@@ -0,0 +1,142 @@
+use crate::save::calculate_checksum;
+use crate::{HEADER_CHECKSUM_LOCATION_INDEX, HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX, MAX_LOOP_COUNT, SAVE_SLOT_SIZE};
+
+/* Some save files are a flash image with unknown data before and after it, whose exact layout is not documented.
+   Only the embedded flash image is extracted and written back, the data around it are not parsed or checked,
+   so a file of such a format can't be created from a plain save file, only an existing one can be used as template.
+
+   The flash image is found by the "CAMELOT" headers of save blocks.
+   Every header must be at the start of a block in flash image: a TBS block is 0x1000 bytes and there are 16 of them,
+   a TLA block is 0x3000 bytes and there are 5 of them, the game is found by the checksum of each block.
+   If more than one location fits every header, like when the first blocks of flash image are empty,
+   the flash image can't be found without knowing the layout of the file, so it's refused instead of guessed.
+   The data before and after the flash image are kept as they are. */
+const CAMELOT_HEADER: &[u8; 7] = b"CAMELOT";
+const BLOCK_ALIGNMENT: usize = 0x1000;
+const FLASH_IMAGE_SIZE: usize = 0x10000;
+/// The slot numbers of the second half of TBS save data are 3, 4 and 5, 15 is the max slot number.
+const MAX_SLOT_NUMBER: u8 = 0x0F;
+
+pub struct EmbeddedImage {
+  prefix: Vec<u8>,
+  suffix: Vec<u8>,
+}
+
+/// Location of the flash image in file, if there is one.
+pub fn find_flash_image(file_bytes: &[u8]) -> Result<Option<usize>, String> {
+  if file_bytes.len() <= FLASH_IMAGE_SIZE {
+    return Ok(None);
+  }
+
+  // Location and game type of each save block header, the game type is unknown if the checksum of the block is not valid.
+  let mut save_block_headers: Vec<(usize, Option<usize>)> = Vec::new();
+  for location_index in 0..file_bytes.len() - CAMELOT_HEADER.len() {
+    if !file_bytes[location_index..].starts_with(CAMELOT_HEADER) || file_bytes.get(location_index + HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX).is_none_or(|slot_number| *slot_number > MAX_SLOT_NUMBER) {
+      continue;
+    }
+    // A block mostly filled with zeros may match both.
+    let game_type_indexes: Vec<usize> = (0..SAVE_SLOT_SIZE.len()).filter(|game_type_index| is_checksum_valid(file_bytes, location_index, *game_type_index)).collect();
+    let game_type_index_option = if game_type_indexes.len() == 1 { Some(game_type_indexes[0]) } else { None };
+    save_block_headers.push((location_index, game_type_index_option));
+  }
+  let Some(first_header_location_index) = save_block_headers.first().map(|(location_index, _)| *location_index) else {
+    return Ok(None);
+  };
+
+  // Every start of flash image that puts each header at the start of a block, and keeps the flash image in file.
+  let valid_starts: Vec<usize> = (0..=first_header_location_index / BLOCK_ALIGNMENT)
+    .map(|i| first_header_location_index - i * BLOCK_ALIGNMENT)
+    .filter(|start| start + FLASH_IMAGE_SIZE <= file_bytes.len())
+    .filter(|start| save_block_headers.iter().all(|(location_index, game_type_index_option)| is_block_start(location_index - start, *game_type_index_option)))
+    .collect();
+  match valid_starts[..] {
+    [] | [0] => Ok(None),
+    [start] => Ok(Some(start)),
+    _ => Err(format!("The flash image in this save file may start at {}, its location can't be found!", valid_starts.iter().rev().map(|start| format!("{start:#X}")).collect::<Vec<String>>().join(", "))),
+  }
+}
+
+fn is_checksum_valid(file_bytes: &[u8], location_index: usize, game_type_index: usize) -> bool {
+  let Some(raw_block) = file_bytes.get(location_index..location_index + SAVE_SLOT_SIZE[game_type_index]) else {
+    return false;
+  };
+
+  u16::from_le_bytes([raw_block[HEADER_CHECKSUM_LOCATION_INDEX[0]], raw_block[HEADER_CHECKSUM_LOCATION_INDEX[1]]]) == calculate_checksum(raw_block, game_type_index)
+}
+
+/// Whether a header at this offset of flash image is at the start of one of the blocks.
+fn is_block_start(offset: usize, game_type_index_option: Option<usize>) -> bool {
+  match game_type_index_option {
+    Some(game_type_index) => offset.is_multiple_of(SAVE_SLOT_SIZE[game_type_index]) && offset / SAVE_SLOT_SIZE[game_type_index] < MAX_LOOP_COUNT[game_type_index],
+    None => offset.is_multiple_of(BLOCK_ALIGNMENT) && offset < FLASH_IMAGE_SIZE,
+  }
+}
+
+pub fn unwrap(file_bytes: &[u8], start: usize) -> (EmbeddedImage, Vec<u8>) {
+  let embedded_image = EmbeddedImage { prefix: file_bytes[..start].to_vec(), suffix: file_bytes[start + FLASH_IMAGE_SIZE..].to_vec() };
+
+  (embedded_image, file_bytes[start..start + FLASH_IMAGE_SIZE].to_vec())
+}
+
+pub fn wrap(embedded_image: &EmbeddedImage, raw_save_file: &[u8]) -> Vec<u8> {
+  let mut file_bytes = embedded_image.prefix.clone();
+  file_bytes.extend_from_slice(raw_save_file);
+  file_bytes.extend_from_slice(&embedded_image.suffix);
+
+  file_bytes
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::save::update_checksum;
+
+  /// A flash image with unknown data before and after it, and a save block with valid checksum at each offset.
+  fn create_file(game_type_index: usize, block_offsets: &[usize], prefix_size: usize, suffix_size: usize) -> Vec<u8> {
+    let mut raw_save_file = vec![0xFFu8; FLASH_IMAGE_SIZE];
+    for (slot_number, block_offset) in block_offsets.iter().enumerate() {
+      let raw_block = &mut raw_save_file[*block_offset..*block_offset + SAVE_SLOT_SIZE[game_type_index]];
+      raw_block.fill(0x00);
+      raw_block[..CAMELOT_HEADER.len()].copy_from_slice(CAMELOT_HEADER);
+      raw_block[HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX] = slot_number as u8;
+      raw_block[0x10] = 0x42;
+      raw_block[SAVE_SLOT_SIZE[game_type_index] - 1] = 0x42;
+      update_checksum(raw_block, game_type_index);
+    }
+    let mut file_bytes = vec![0x11u8; prefix_size];
+    file_bytes.extend_from_slice(&raw_save_file);
+    file_bytes.extend(std::iter::repeat_n(0x22u8, suffix_size));
+    file_bytes
+  }
+
+  #[test]
+  fn find_image_starting_with_a_block() {
+    assert_eq!(find_flash_image(&create_file(1, &[0x0000, 0x3000], 0x30, 0x100)), Ok(Some(0x30)));
+    assert_eq!(find_flash_image(&create_file(0, &[0x0000, 0xF000], 0x30, 0x2000)), Ok(Some(0x30)));
+  }
+
+  #[test]
+  fn tla_blocks_are_at_multiples_of_block_size() {
+    // The first block is empty, the flash image can't start at 0x1030 with TLA blocks at 0x2000 and 0x5000.
+    assert_eq!(find_flash_image(&create_file(1, &[0x3000, 0x6000], 0x30, 0x1000)), Ok(Some(0x30)));
+  }
+
+  #[test]
+  fn ambiguous_location_is_refused() {
+    assert!(find_flash_image(&create_file(0, &[0x1000, 0x2000], 0x30, 0x2000)).is_err());
+  }
+
+  #[test]
+  fn plain_image_with_trailing_data_is_not_embedded() {
+    assert_eq!(find_flash_image(&create_file(0, &[0x0000, 0xF000], 0, 0x30)), Ok(None));
+    assert_eq!(find_flash_image(&create_file(0, &[0x0000], 0, 0)), Ok(None));
+  }
+
+  #[test]
+  fn unwrap_and_wrap_round_trip() {
+    let file_bytes = create_file(1, &[0x0000, 0x3000], 0x30, 0x100);
+    let (embedded_image, raw_save_file) = unwrap(&file_bytes, 0x30);
+    assert_eq!(raw_save_file[..CAMELOT_HEADER.len()], *CAMELOT_HEADER);
+    assert_eq!(wrap(&embedded_image, &raw_save_file), file_bytes);
+  }
+}
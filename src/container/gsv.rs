@@ -0,0 +1,79 @@
+use super::ROM_GAME_TITLE_LOCATION_INDEX;
+
+/* GameShark SP save file (.gsv), created by the GameShark SP/Action Replay PC link software.
+   Source: mGBA "src/gba/sharkport.c"
+
+   0x000  "ADVSAVEG"
+   0x00C  ROM header 0xA0 - 0xAF (game title and game code)
+   0x42C  0x12345678
+   0x430  Save data
+
+   Other bytes of the header are not documented, they are kept as they are. */
+const GSV_MAGIC: &[u8; 8] = b"ADVSAVEG";
+const GSV_GAME_INFO_LOCATION_INDEX: usize = 0x0C;
+const GSV_FOOTER_LOCATION_INDEX: usize = 0x42C;
+const GSV_FOOTER: u32 = 0x1234_5678;
+const GSV_HEADER_SIZE: usize = 0x430;
+
+pub struct GsvHeader {
+  raw_header: Vec<u8>,
+}
+
+pub fn is_gsv_save(file_bytes: &[u8]) -> bool {
+  file_bytes.starts_with(GSV_MAGIC)
+}
+
+pub fn unwrap(file_bytes: &[u8]) -> Result<(GsvHeader, Vec<u8>), String> {
+  if file_bytes.len() <= GSV_HEADER_SIZE {
+    return Err(String::from("GameShark SP save file is truncated!"));
+  }
+
+  Ok((GsvHeader { raw_header: file_bytes[..GSV_HEADER_SIZE].to_vec() }, file_bytes[GSV_HEADER_SIZE..].to_vec()))
+}
+
+/// Create a new header from the header of game ROM (the first 0xC0 bytes).
+pub fn create_header(rom_header: &[u8]) -> GsvHeader {
+  let mut raw_header = vec![0u8; GSV_HEADER_SIZE];
+  raw_header[..GSV_MAGIC.len()].copy_from_slice(GSV_MAGIC);
+  raw_header[GSV_GAME_INFO_LOCATION_INDEX..GSV_GAME_INFO_LOCATION_INDEX + 0x10].copy_from_slice(&rom_header[ROM_GAME_TITLE_LOCATION_INDEX..ROM_GAME_TITLE_LOCATION_INDEX + 0x10]);
+  raw_header[GSV_FOOTER_LOCATION_INDEX..GSV_FOOTER_LOCATION_INDEX + 4].copy_from_slice(&GSV_FOOTER.to_le_bytes());
+
+  GsvHeader { raw_header }
+}
+
+pub fn wrap(header: &GsvHeader, raw_save_file: &[u8]) -> Vec<u8> {
+  let mut file_bytes = header.raw_header.clone();
+  file_bytes.extend_from_slice(raw_save_file);
+
+  file_bytes
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::container::ROM_HEADER_SIZE;
+
+  #[test]
+  fn create_and_unwrap_round_trip() {
+    let mut rom_header = vec![0u8; ROM_HEADER_SIZE];
+    rom_header[ROM_GAME_TITLE_LOCATION_INDEX..ROM_GAME_TITLE_LOCATION_INDEX + 16].copy_from_slice(b"GOLDEN_SUN_AAGSE");
+    let raw_save_file = vec![0x5Au8; 0x100];
+    let file_bytes = wrap(&create_header(&rom_header), &raw_save_file);
+    assert!(is_gsv_save(&file_bytes));
+    assert_eq!(file_bytes[GSV_GAME_INFO_LOCATION_INDEX..GSV_GAME_INFO_LOCATION_INDEX + 16], *b"GOLDEN_SUN_AAGSE");
+    assert_eq!(file_bytes[GSV_FOOTER_LOCATION_INDEX..GSV_FOOTER_LOCATION_INDEX + 4], [0x78, 0x56, 0x34, 0x12]);
+
+    let (header, unwrapped_save_file) = unwrap(&file_bytes).unwrap();
+    assert_eq!(unwrapped_save_file, raw_save_file);
+    assert_eq!(wrap(&header, &unwrapped_save_file), file_bytes);
+  }
+
+  #[test]
+  fn truncated_file_is_refused() {
+    let mut file_bytes = GSV_MAGIC.to_vec();
+    file_bytes.resize(GSV_HEADER_SIZE, 0);
+    assert!(is_gsv_save(&file_bytes));
+    assert!(unwrap(&file_bytes).is_err());
+    assert!(unwrap(GSV_MAGIC).is_err());
+  }
+}
@@ -0,0 +1,224 @@
+use super::read_u32;
+use super::zlib::{zlib_compress, zlib_decompress};
+use crate::hash::crc32;
+use crate::save::FLASH_BANK_SIZE;
+
+/* mGBA save states can embed the cartridge save data as "extdata".
+   Source: mGBA "src/core/serialize.c" and "include/mgba/core/serialize.h"
+
+   Raw save state:
+   0x00000  GBA state, starts with 0x01000000 + state version, 0x61000 bytes
+   0x61000  Extdata headers, 16 bytes each: tag (u32), size (s32), offset from the start of file (s64)
+            A header with tag 0 ends the list.
+
+   PNG save state (default when mGBA is built with libpng):
+   The screenshot is the PNG image itself, the state is in a "gbAs" chunk (zlib compressed),
+   and every extdata is in a "gbAx" chunk: tag (u32), uncompressed size (u32), zlib compressed data. */
+const MGBA_STATE_MAGIC: u32 = 0x0100_0000;
+const MGBA_STATE_SIZE: usize = 0x61000;
+const MGBA_EXTDATA_HEADER_SIZE: usize = 0x10;
+const MAX_SAVEDATA_SIZE: usize = FLASH_BANK_SIZE * 2;
+const MGBA_EXTDATA_NONE: u32 = 0;
+const MGBA_EXTDATA_SAVEDATA: u32 = 2;
+
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1A\n";
+const PNG_STATE_CHUNK_TYPE: &[u8; 4] = b"gbAs";
+const PNG_EXTDATA_CHUNK_TYPE: &[u8; 4] = b"gbAx";
+
+pub enum MgbaStateLayout {
+  // Location of the save data in raw save state.
+  Raw { savedata_location_index: usize },
+  // Location of the whole "gbAx" chunk with save data in PNG save state.
+  Png { chunk_location_index: usize, chunk_size: usize },
+}
+
+pub struct MgbaState {
+  file_bytes: Vec<u8>,
+  layout: MgbaStateLayout,
+}
+
+impl MgbaState {
+  /// Save data in raw save state is written back in place, PNG save state rebuilds its chunk.
+  pub fn is_fixed_size(&self) -> bool {
+    matches!(self.layout, MgbaStateLayout::Raw { .. })
+  }
+}
+
+pub fn is_mgba_state(file_bytes: &[u8]) -> bool {
+  if file_bytes.starts_with(PNG_SIGNATURE) {
+    return get_png_chunks(file_bytes).iter().any(|png_chunk| &file_bytes[png_chunk.type_range()] == PNG_STATE_CHUNK_TYPE);
+  }
+
+  file_bytes.len() > MGBA_STATE_SIZE && read_u32(file_bytes, 0) & 0xFF00_0000 == MGBA_STATE_MAGIC
+}
+
+pub fn unwrap(file_bytes: Vec<u8>) -> Result<(MgbaState, Vec<u8>), String> {
+  let no_savedata_error = || String::from("There is no save data in this mGBA save state!");
+
+  if file_bytes.starts_with(PNG_SIGNATURE) {
+    for png_chunk in get_png_chunks(&file_bytes) {
+      let chunk_data = &file_bytes[png_chunk.data_range()];
+      if &file_bytes[png_chunk.type_range()] != PNG_EXTDATA_CHUNK_TYPE || chunk_data.len() < 8 || read_u32(chunk_data, 0) != MGBA_EXTDATA_SAVEDATA {
+        continue;
+      }
+
+      // The declared size limits decompression, and a save state never has more save data than a 128KB flash.
+      let size = read_u32(chunk_data, 4) as usize;
+      if size > MAX_SAVEDATA_SIZE {
+        return Err(String::from("The size of save data in mGBA save state is not valid!"));
+      }
+      let raw_save_file = zlib_decompress(&chunk_data[8..], size)?;
+      if raw_save_file.len() != size {
+        return Err(String::from("The size of save data in mGBA save state is not valid!"));
+      }
+      let layout = MgbaStateLayout::Png { chunk_location_index: png_chunk.location_index, chunk_size: png_chunk.size() };
+      return Ok((MgbaState { file_bytes, layout }, raw_save_file));
+    }
+    return Err(no_savedata_error());
+  }
+
+  let mut header_location_index = MGBA_STATE_SIZE;
+  while let Some(header) = file_bytes.get(header_location_index..header_location_index + MGBA_EXTDATA_HEADER_SIZE) {
+    let tag = read_u32(header, 0);
+    if tag == MGBA_EXTDATA_NONE {
+      break;
+    }
+    if tag == MGBA_EXTDATA_SAVEDATA {
+      let size = read_u32(header, 4) as usize;
+      let savedata_location_index = usize::try_from(u64::from(read_u32(header, 8)) | u64::from(read_u32(header, 12)) << 32).map_err(|_| no_savedata_error())?;
+      let Some(raw_save_file) = savedata_location_index.checked_add(size).and_then(|savedata_end| file_bytes.get(savedata_location_index..savedata_end)) else {
+        return Err(String::from("mGBA save state is truncated!"));
+      };
+      let raw_save_file = raw_save_file.to_vec();
+      return Ok((MgbaState { file_bytes, layout: MgbaStateLayout::Raw { savedata_location_index } }, raw_save_file));
+    }
+    header_location_index += MGBA_EXTDATA_HEADER_SIZE;
+  }
+
+  Err(no_savedata_error())
+}
+
+/// Put the save data back into the save state, everything else in the save state is kept as it is.
+pub fn wrap(mgba_state: &MgbaState, raw_save_file: &[u8]) -> Vec<u8> {
+  match mgba_state.layout {
+    // The size of save data is never changed by conversion.
+    MgbaStateLayout::Raw { savedata_location_index } => {
+      let mut file_bytes = mgba_state.file_bytes.clone();
+      file_bytes[savedata_location_index..savedata_location_index + raw_save_file.len()].copy_from_slice(raw_save_file);
+      file_bytes
+    }
+    MgbaStateLayout::Png { chunk_location_index, chunk_size } => {
+      let mut chunk_data = Vec::new();
+      chunk_data.extend_from_slice(&MGBA_EXTDATA_SAVEDATA.to_le_bytes());
+      chunk_data.extend_from_slice(&(raw_save_file.len() as u32).to_le_bytes());
+      chunk_data.extend_from_slice(&zlib_compress(raw_save_file));
+
+      let mut file_bytes = mgba_state.file_bytes[..chunk_location_index].to_vec();
+      file_bytes.extend_from_slice(&create_png_chunk(PNG_EXTDATA_CHUNK_TYPE, &chunk_data));
+      file_bytes.extend_from_slice(&mgba_state.file_bytes[chunk_location_index + chunk_size..]);
+      file_bytes
+    }
+  }
+}
+
+/// PNG chunk: length (u32, big-endian), type (4 bytes), data, CRC-32 of type and data (u32, big-endian).
+struct PngChunk {
+  location_index: usize,
+  data_size: usize,
+}
+
+impl PngChunk {
+  fn type_range(&self) -> std::ops::Range<usize> {
+    self.location_index + 4..self.location_index + 8
+  }
+
+  fn data_range(&self) -> std::ops::Range<usize> {
+    self.location_index + 8..self.location_index + 8 + self.data_size
+  }
+
+  fn size(&self) -> usize {
+    self.data_size + 12
+  }
+}
+
+fn get_png_chunks(file_bytes: &[u8]) -> Vec<PngChunk> {
+  let mut png_chunks = Vec::new();
+  let mut location_index = PNG_SIGNATURE.len();
+  while location_index + 12 <= file_bytes.len() {
+    let data_size = u32::from_be_bytes([file_bytes[location_index], file_bytes[location_index + 1], file_bytes[location_index + 2], file_bytes[location_index + 3]]) as usize;
+    if data_size > file_bytes.len() - location_index - 12 {
+      break;
+    }
+    png_chunks.push(PngChunk { location_index, data_size });
+    location_index += 12 + data_size;
+  }
+
+  png_chunks
+}
+
+fn create_png_chunk(chunk_type: &[u8; 4], chunk_data: &[u8]) -> Vec<u8> {
+  let mut png_chunk = Vec::new();
+  png_chunk.extend_from_slice(&(chunk_data.len() as u32).to_be_bytes());
+  png_chunk.extend_from_slice(chunk_type);
+  png_chunk.extend_from_slice(chunk_data);
+  png_chunk.extend_from_slice(&crc32(&png_chunk[4..]).to_be_bytes());
+
+  png_chunk
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn create_raw_state(savedata_location_index: u64, size: u32) -> Vec<u8> {
+    let mut file_bytes = vec![0u8; MGBA_STATE_SIZE + MGBA_EXTDATA_HEADER_SIZE * 2];
+    file_bytes[..4].copy_from_slice(&(MGBA_STATE_MAGIC | 0x08).to_le_bytes());
+    file_bytes[MGBA_STATE_SIZE..MGBA_STATE_SIZE + 4].copy_from_slice(&MGBA_EXTDATA_SAVEDATA.to_le_bytes());
+    file_bytes[MGBA_STATE_SIZE + 4..MGBA_STATE_SIZE + 8].copy_from_slice(&size.to_le_bytes());
+    file_bytes[MGBA_STATE_SIZE + 8..MGBA_STATE_SIZE + 16].copy_from_slice(&savedata_location_index.to_le_bytes());
+    file_bytes
+  }
+
+  #[test]
+  fn raw_state_round_trip() {
+    let mut file_bytes = create_raw_state((MGBA_STATE_SIZE + MGBA_EXTDATA_HEADER_SIZE * 2) as u64, 4);
+    file_bytes.extend_from_slice(&[1, 2, 3, 4]);
+    assert!(is_mgba_state(&file_bytes));
+    let (mgba_state, raw_save_file) = unwrap(file_bytes.clone()).unwrap();
+    assert_eq!(raw_save_file, [1, 2, 3, 4]);
+    assert_eq!(wrap(&mgba_state, &raw_save_file), file_bytes);
+  }
+
+  #[test]
+  fn savedata_out_of_range_is_refused() {
+    for (savedata_location_index, size) in [(u64::MAX, 0x10), (u64::MAX - 0x0F, 0x10), (MGBA_STATE_SIZE as u64, 0x10000)] {
+      assert!(unwrap(create_raw_state(savedata_location_index, size)).is_err());
+    }
+  }
+
+  #[test]
+  fn png_chunk_longer_than_file_is_ignored() {
+    let mut file_bytes = PNG_SIGNATURE.to_vec();
+    file_bytes.extend_from_slice(&create_png_chunk(b"IHDR", &[0; 13]));
+    file_bytes.extend_from_slice(&u32::MAX.to_be_bytes());
+    file_bytes.extend_from_slice(PNG_STATE_CHUNK_TYPE);
+    file_bytes.extend_from_slice(&[0; 8]);
+    assert_eq!(get_png_chunks(&file_bytes).len(), 1);
+    assert!(!is_mgba_state(&file_bytes));
+  }
+
+  #[test]
+  fn png_savedata_is_limited_to_its_declared_size() {
+    let create_png_state = |declared_size: u32, raw_save_file: &[u8]| {
+      let mut chunk_data = MGBA_EXTDATA_SAVEDATA.to_le_bytes().to_vec();
+      chunk_data.extend_from_slice(&declared_size.to_le_bytes());
+      chunk_data.extend_from_slice(&zlib_compress(raw_save_file));
+      let mut file_bytes = PNG_SIGNATURE.to_vec();
+      file_bytes.extend_from_slice(&create_png_chunk(PNG_EXTDATA_CHUNK_TYPE, &chunk_data));
+      file_bytes
+    };
+    assert_eq!(unwrap(create_png_state(4, &[1, 2, 3, 4])).unwrap().1, [1, 2, 3, 4]);
+    assert!(unwrap(create_png_state(3, &[1, 2, 3, 4])).is_err());
+    assert!(unwrap(create_png_state(0x3_0000, &vec![0; 0x3_0000])).is_err());
+  }
+}
@@ -0,0 +1,175 @@
+use super::read_u32;
+
+/* No$GBA stores backup media in its own .sav format:
+
+   0x00  "NocashGbaBackupMediaSavDataFile" + 0x1A
+   0x20  Other information like media type and date, kept as it is
+   0x40  "SRAM"
+   0x44  Compression (0 = uncompressed, 1 = compressed)
+   0x48  Size of data in file
+   0x4C  Size of data when uncompressed
+   0x50  Data
+
+   Compressed data is a simple RLE stream:
+   0x00         End of data
+   0x01 - 0x7F  Copy the next N bytes
+   0x80         Fill: the next 2 bytes (little-endian) are the count, the byte after them is the value
+   0x81 - 0xFF  Fill: repeat the next byte (N - 0x80) times */
+const NOCASH_GBA_MAGIC: &[u8; 32] = b"NocashGbaBackupMediaSavDataFile\x1A";
+const NOCASH_GBA_MEDIA_TAG: &[u8; 4] = b"SRAM";
+const NOCASH_GBA_MEDIA_TAG_LOCATION_INDEX: usize = 0x40;
+const NOCASH_GBA_COMPRESSION_LOCATION_INDEX: usize = 0x44;
+const NOCASH_GBA_DATA_SIZE_LOCATION_INDEX: usize = 0x48;
+const NOCASH_GBA_RAW_SIZE_LOCATION_INDEX: usize = 0x4C;
+const NOCASH_GBA_HEADER_SIZE: usize = 0x50;
+
+/// RLE only pays off for runs of at least this many bytes.
+const MIN_FILL_LENGTH: usize = 3;
+
+pub struct NoCashGbaHeader {
+  raw_header: Vec<u8>,
+  is_compressed: bool,
+}
+
+pub fn is_nocash_gba_save(file_bytes: &[u8]) -> bool {
+  file_bytes.starts_with(NOCASH_GBA_MAGIC)
+}
+
+pub fn unwrap(file_bytes: &[u8]) -> Result<(NoCashGbaHeader, Vec<u8>), String> {
+  if file_bytes.len() < NOCASH_GBA_HEADER_SIZE || &file_bytes[NOCASH_GBA_MEDIA_TAG_LOCATION_INDEX..NOCASH_GBA_MEDIA_TAG_LOCATION_INDEX + 4] != NOCASH_GBA_MEDIA_TAG {
+    return Err(String::from("Unsupported No$GBA save file!"));
+  }
+
+  let is_compressed = match read_u32(file_bytes, NOCASH_GBA_COMPRESSION_LOCATION_INDEX) {
+    0 => false,
+    1 => true,
+    _ => return Err(String::from("Unknown compression in No$GBA save file!")),
+  };
+  let data_size = read_u32(file_bytes, NOCASH_GBA_DATA_SIZE_LOCATION_INDEX) as usize;
+  let raw_size = read_u32(file_bytes, NOCASH_GBA_RAW_SIZE_LOCATION_INDEX) as usize;
+  let Some(data) = file_bytes.get(NOCASH_GBA_HEADER_SIZE..NOCASH_GBA_HEADER_SIZE + data_size) else {
+    return Err(String::from("No$GBA save file is truncated!"));
+  };
+
+  let raw_save_file = if is_compressed { decompress(data)? } else { data.to_vec() };
+  if raw_save_file.len() != raw_size {
+    return Err(String::from("The size of data in No$GBA save file is not valid!"));
+  }
+
+  Ok((NoCashGbaHeader { raw_header: file_bytes[..NOCASH_GBA_HEADER_SIZE].to_vec(), is_compressed }, raw_save_file))
+}
+
+pub fn wrap(header: &NoCashGbaHeader, raw_save_file: &[u8]) -> Vec<u8> {
+  let data = if header.is_compressed { compress(raw_save_file) } else { raw_save_file.to_vec() };
+
+  let mut file_bytes = header.raw_header.clone();
+  file_bytes[NOCASH_GBA_DATA_SIZE_LOCATION_INDEX..NOCASH_GBA_DATA_SIZE_LOCATION_INDEX + 4].copy_from_slice(&(data.len() as u32).to_le_bytes());
+  file_bytes[NOCASH_GBA_RAW_SIZE_LOCATION_INDEX..NOCASH_GBA_RAW_SIZE_LOCATION_INDEX + 4].copy_from_slice(&(raw_save_file.len() as u32).to_le_bytes());
+  file_bytes.extend_from_slice(&data);
+
+  file_bytes
+}
+
+fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+  let truncated_error = || String::from("Compressed data in No$GBA save file is truncated!");
+  let mut raw_save_file = Vec::new();
+  let mut i = 0;
+  loop {
+    let command = *data.get(i).ok_or_else(truncated_error)?;
+    i += 1;
+    match command {
+      0x00 => break,
+      0x01..=0x7F => {
+        let literal = data.get(i..i + usize::from(command)).ok_or_else(truncated_error)?;
+        raw_save_file.extend_from_slice(literal);
+        i += usize::from(command);
+      }
+      0x80 => {
+        let fill = data.get(i..i + 3).ok_or_else(truncated_error)?;
+        raw_save_file.extend(std::iter::repeat_n(fill[2], usize::from(u16::from_le_bytes([fill[0], fill[1]]))));
+        i += 3;
+      }
+      _ => {
+        let value = *data.get(i).ok_or_else(truncated_error)?;
+        raw_save_file.extend(std::iter::repeat_n(value, usize::from(command - 0x80)));
+        i += 1;
+      }
+    }
+  }
+
+  Ok(raw_save_file)
+}
+
+fn compress(raw_save_file: &[u8]) -> Vec<u8> {
+  let mut data = Vec::new();
+  let mut literal_start = 0;
+  let mut i = 0;
+  while i < raw_save_file.len() {
+    let run_length = raw_save_file[i..].iter().take(usize::from(u16::MAX)).take_while(|byte| **byte == raw_save_file[i]).count();
+    if run_length < MIN_FILL_LENGTH {
+      i += 1;
+      continue;
+    }
+
+    push_literals(&mut data, &raw_save_file[literal_start..i]);
+    if run_length <= 0x7F {
+      data.push(0x80 + run_length as u8);
+      data.push(raw_save_file[i]);
+    } else {
+      data.push(0x80);
+      data.extend_from_slice(&(run_length as u16).to_le_bytes());
+      data.push(raw_save_file[i]);
+    }
+    i += run_length;
+    literal_start = i;
+  }
+  push_literals(&mut data, &raw_save_file[literal_start..]);
+  data.push(0x00);
+
+  data
+}
+
+fn push_literals(data: &mut Vec<u8>, literals: &[u8]) {
+  for chunk in literals.chunks(0x7F) {
+    data.push(chunk.len() as u8);
+    data.extend_from_slice(chunk);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn decompress_all_commands() {
+    // Written by hand from the format description above, not by `compress`.
+    let data = [0x02, 0xAA, 0xBB, 0x80, 0x00, 0x01, 0xFF, 0x83, 0x11, 0x00];
+    let mut expected = vec![0xAA, 0xBB];
+    expected.extend([0xFF; 0x100]);
+    expected.extend([0x11; 3]);
+    assert_eq!(decompress(&data).unwrap(), expected);
+  }
+
+  #[test]
+  fn compress_long_fill() {
+    let mut raw_save_file = vec![0x12];
+    raw_save_file.extend([0xFF; 0x1234]);
+    assert_eq!(compress(&raw_save_file), [0x01, 0x12, 0x80, 0x34, 0x12, 0xFF, 0x00]);
+  }
+
+  #[test]
+  fn compress_round_trip() {
+    let mut raw_save_file: Vec<u8> = (0..0x300).map(|i| (i * 7 % 251) as u8).collect();
+    raw_save_file.extend([0xFF; 0x10000]);
+    raw_save_file.extend([0x00; 0x50]);
+    raw_save_file.extend([0x01, 0x01, 0x02]);
+    assert_eq!(decompress(&compress(&raw_save_file)).unwrap(), raw_save_file);
+  }
+
+  #[test]
+  fn decompress_truncated_data() {
+    for data in [&[][..], &[0x03, 0x01, 0x02], &[0x80, 0x00, 0x01], &[0x85], &[0x01, 0x01]] {
+      assert!(decompress(data).is_err());
+    }
+  }
+}
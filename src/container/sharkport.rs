@@ -0,0 +1,178 @@
+use super::{read_u32, ROM_GAME_TITLE_LOCATION_INDEX};
+
+/* SharkPort save file (.sps/.xps), used by Action Replay/GameShark PC link software.
+   Source: VBA-M "CPUWriteGSASnapshot" and mGBA "src/gba/sharkport.c"
+
+   Every string is stored as its length (u32) followed by its bytes.
+   String "SharkPortSave"
+   u32    Platform, always 0x000F0000 for GBA
+   String Title
+   String Description
+   String Notes
+   u32    Size of game information + save data
+   0x1C   Game information:
+          0x00 ROM header 0xA0 - 0xAF (game title and game code)
+          0x10 ROM header 0xBE, 0xBF, 0xBD (header checksum), 0xB0
+          0x14 Always 1
+   ...    Save data
+   u32    Checksum of game information and save data */
+const SHARKPORT_MAGIC: &[u8; 13] = b"SharkPortSave";
+const SHARKPORT_GBA_PLATFORM: u32 = 0x000F_0000;
+const SHARKPORT_GAME_INFO_SIZE: usize = 0x1C;
+
+pub struct SharkPortHeader {
+  title: Vec<u8>,
+  description: Vec<u8>,
+  notes: Vec<u8>,
+  game_info: Vec<u8>,
+  /* The checksum adds each byte as a C "char", which is signed on some platforms.
+     Keep the variant the input file uses, new files use unsigned bytes like VBA-M. */
+  is_checksum_signed: bool,
+}
+
+pub fn is_sharkport_save(file_bytes: &[u8]) -> bool {
+  file_bytes.len() >= 4 + SHARKPORT_MAGIC.len() && read_u32(file_bytes, 0) as usize == SHARKPORT_MAGIC.len() && file_bytes[4..4 + SHARKPORT_MAGIC.len()] == *SHARKPORT_MAGIC
+}
+
+pub fn unwrap(file_bytes: &[u8]) -> Result<(SharkPortHeader, Vec<u8>), String> {
+  let truncated_error = || String::from("SharkPort save file is truncated!");
+  let mut location_index = 4 + SHARKPORT_MAGIC.len();
+
+  let read_next_u32 = |location_index: &mut usize| -> Result<u32, String> {
+    let value = file_bytes.get(*location_index..*location_index + 4).map(|bytes| read_u32(bytes, 0)).ok_or_else(truncated_error)?;
+    *location_index += 4;
+    Ok(value)
+  };
+  let read_next_bytes = |location_index: &mut usize, size: usize| -> Result<Vec<u8>, String> {
+    let bytes = file_bytes.get(*location_index..*location_index + size).ok_or_else(truncated_error)?.to_vec();
+    *location_index += size;
+    Ok(bytes)
+  };
+
+  if read_next_u32(&mut location_index)? != SHARKPORT_GBA_PLATFORM {
+    return Err(String::from("It's not a GBA SharkPort save file!"));
+  }
+  let read_next_string = |location_index: &mut usize| -> Result<Vec<u8>, String> {
+    let size = read_next_u32(location_index)? as usize;
+    read_next_bytes(location_index, size)
+  };
+  let title = read_next_string(&mut location_index)?;
+  let description = read_next_string(&mut location_index)?;
+  let notes = read_next_string(&mut location_index)?;
+
+  let data_size = read_next_u32(&mut location_index)? as usize;
+  if data_size < SHARKPORT_GAME_INFO_SIZE {
+    return Err(String::from("The size of save data in SharkPort save file is not valid!"));
+  }
+  let game_info = read_next_bytes(&mut location_index, SHARKPORT_GAME_INFO_SIZE)?;
+  let raw_save_file = read_next_bytes(&mut location_index, data_size - SHARKPORT_GAME_INFO_SIZE)?;
+  let checksum = read_next_u32(&mut location_index)?;
+
+  // Some tools write a wrong checksum, so it's not treated as an error, the checksum is always recalculated when writing.
+  let is_checksum_signed = checksum != calculate_checksum(&game_info, &raw_save_file, false) && checksum == calculate_checksum(&game_info, &raw_save_file, true);
+
+  Ok((SharkPortHeader { title, description, notes, game_info, is_checksum_signed }, raw_save_file))
+}
+
+/// Create a new header from the header of game ROM (the first 0xC0 bytes).
+pub fn create_header(rom_header: &[u8]) -> SharkPortHeader {
+  let mut game_info = vec![0u8; SHARKPORT_GAME_INFO_SIZE];
+  game_info[..0x10].copy_from_slice(&rom_header[ROM_GAME_TITLE_LOCATION_INDEX..ROM_GAME_TITLE_LOCATION_INDEX + 0x10]);
+  game_info[0x10] = rom_header[0xBE];
+  game_info[0x11] = rom_header[0xBF];
+  game_info[0x12] = rom_header[0xBD];
+  game_info[0x13] = rom_header[0xB0];
+  game_info[0x14] = 1;
+
+  // Game title in ROM header is padded with zeros.
+  let title = rom_header[ROM_GAME_TITLE_LOCATION_INDEX..ROM_GAME_TITLE_LOCATION_INDEX + 12].iter().copied().take_while(|byte| *byte != 0).collect();
+
+  SharkPortHeader { title, description: Vec::new(), notes: b"Golden Sun Save Converter".to_vec(), game_info, is_checksum_signed: false }
+}
+
+pub fn wrap(header: &SharkPortHeader, raw_save_file: &[u8]) -> Vec<u8> {
+  let mut file_bytes = Vec::new();
+  push_string(&mut file_bytes, SHARKPORT_MAGIC);
+  file_bytes.extend_from_slice(&SHARKPORT_GBA_PLATFORM.to_le_bytes());
+  push_string(&mut file_bytes, &header.title);
+  push_string(&mut file_bytes, &header.description);
+  push_string(&mut file_bytes, &header.notes);
+  file_bytes.extend_from_slice(&((SHARKPORT_GAME_INFO_SIZE + raw_save_file.len()) as u32).to_le_bytes());
+  file_bytes.extend_from_slice(&header.game_info);
+  file_bytes.extend_from_slice(raw_save_file);
+  file_bytes.extend_from_slice(&calculate_checksum(&header.game_info, raw_save_file, header.is_checksum_signed).to_le_bytes());
+
+  file_bytes
+}
+
+fn push_string(file_bytes: &mut Vec<u8>, string: &[u8]) {
+  file_bytes.extend_from_slice(&(string.len() as u32).to_le_bytes());
+  file_bytes.extend_from_slice(string);
+}
+
+fn calculate_checksum(game_info: &[u8], raw_save_file: &[u8], is_checksum_signed: bool) -> u32 {
+  let mut checksum = 0u32;
+  for byte in game_info.iter().chain(raw_save_file) {
+    let value = if is_checksum_signed { *byte as i8 as u32 } else { u32::from(*byte) };
+    checksum = checksum.wrapping_add(value << (checksum % 0x18));
+  }
+
+  checksum
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::container::ROM_HEADER_SIZE;
+
+  fn create_rom_header() -> Vec<u8> {
+    let mut rom_header = vec![0u8; ROM_HEADER_SIZE];
+    rom_header[ROM_GAME_TITLE_LOCATION_INDEX..ROM_GAME_TITLE_LOCATION_INDEX + 16].copy_from_slice(b"Golden_Sun_BAGSE");
+    rom_header[0xB0] = 0x30;
+    rom_header[0xBD] = 0x12;
+    rom_header
+  }
+
+  #[test]
+  fn create_and_unwrap_round_trip() {
+    let raw_save_file: Vec<u8> = (0..0x100).map(|i| i as u8).collect();
+    let file_bytes = wrap(&create_header(&create_rom_header()), &raw_save_file);
+    assert!(is_sharkport_save(&file_bytes));
+
+    let (header, unwrapped_save_file) = unwrap(&file_bytes).unwrap();
+    assert_eq!(header.title, b"Golden_Sun_B");
+    assert_eq!(header.game_info[0x12], 0x12);
+    assert_eq!(unwrapped_save_file, raw_save_file);
+    assert_eq!(wrap(&header, &unwrapped_save_file), file_bytes);
+  }
+
+  #[test]
+  fn signed_checksum_is_kept() {
+    let raw_save_file = vec![0xFFu8; 0x40];
+    let mut header = create_header(&create_rom_header());
+    header.is_checksum_signed = true;
+    let file_bytes = wrap(&header, &raw_save_file);
+
+    let (unwrapped_header, _) = unwrap(&file_bytes).unwrap();
+    assert!(unwrapped_header.is_checksum_signed);
+    assert_eq!(wrap(&unwrapped_header, &raw_save_file), file_bytes);
+  }
+
+  #[test]
+  fn malformed_files_are_refused() {
+    let file_bytes = wrap(&create_header(&create_rom_header()), &[0u8; 0x40]);
+    for size in [0, 17, 30, file_bytes.len() - 1] {
+      assert!(unwrap(&file_bytes[..size]).is_err(), "{size}");
+    }
+
+    // Not GBA.
+    let mut other_platform_file_bytes = file_bytes.clone();
+    other_platform_file_bytes[17..21].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+    assert!(unwrap(&other_platform_file_bytes).is_err());
+
+    // A title longer than the file.
+    let mut long_title_file_bytes = file_bytes.clone();
+    long_title_file_bytes[21..25].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(unwrap(&long_title_file_bytes).is_err());
+  }
+}
@@ -0,0 +1,70 @@
+/* Some dumping tools and flashcarts write flash images with the bytes of every 16-bit or 32-bit word swapped.
+   16-bit: "AC" "EM" "OL" "\x01T" -> "CAMELOT" + slot number 1
+   32-bit: "EMAC" "\x01TOL" -> "CAMELOT" + slot number 1
+   The "CAMELOT" headers of save blocks are used to find out which one it is. */
+const CAMELOT_HEADER: &[u8; 7] = b"CAMELOT";
+const BLOCK_ALIGNMENT: usize = 0x1000;
+
+#[derive(Clone, Copy)]
+pub enum ByteSwap {
+  Swap16,
+  Swap32,
+}
+
+impl ByteSwap {
+  fn word_size(self) -> usize {
+    match self {
+      Self::Swap16 => 2,
+      Self::Swap32 => 4,
+    }
+  }
+}
+
+/// Find out whether a flash image is byte-swapped, a flash image with save blocks in native byte order is not.
+pub fn find_byte_swap(raw_save_file: &[u8]) -> Option<ByteSwap> {
+  if has_save_blocks(raw_save_file) {
+    return None;
+  }
+
+  [ByteSwap::Swap16, ByteSwap::Swap32].into_iter().find(|byte_swap| has_save_blocks(&swap_bytes(raw_save_file, *byte_swap)))
+}
+
+/// Swapping the bytes again restores the original order.
+pub fn swap_bytes(raw_save_file: &[u8], byte_swap: ByteSwap) -> Vec<u8> {
+  raw_save_file.chunks(byte_swap.word_size()).flat_map(|word| word.iter().rev().copied()).collect()
+}
+
+fn has_save_blocks(raw_save_file: &[u8]) -> bool {
+  raw_save_file.chunks(BLOCK_ALIGNMENT).any(|raw_block| raw_block.starts_with(CAMELOT_HEADER))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn create_flash_image() -> Vec<u8> {
+    let mut raw_save_file = vec![0xFFu8; 0x10000];
+    raw_save_file[0x3000..0x3007].copy_from_slice(CAMELOT_HEADER);
+    raw_save_file[0x3007] = 0x01;
+    raw_save_file
+  }
+
+  #[test]
+  fn native_byte_order_is_not_swapped() {
+    assert!(find_byte_swap(&create_flash_image()).is_none());
+    assert!(find_byte_swap(&vec![0xFFu8; 0x10000]).is_none());
+  }
+
+  #[test]
+  fn swapped_images_are_found_and_restored() {
+    let raw_save_file = create_flash_image();
+    let swapped_16 = swap_bytes(&raw_save_file, ByteSwap::Swap16);
+    let swapped_32 = swap_bytes(&raw_save_file, ByteSwap::Swap32);
+    assert_eq!(swapped_16[0x3000..0x3008], *b"ACEMOL\x01T");
+    assert_eq!(swapped_32[0x3000..0x3008], *b"EMAC\x01TOL");
+    assert!(matches!(find_byte_swap(&swapped_16), Some(ByteSwap::Swap16)));
+    assert!(matches!(find_byte_swap(&swapped_32), Some(ByteSwap::Swap32)));
+    assert_eq!(swap_bytes(&swapped_16, ByteSwap::Swap16), raw_save_file);
+    assert_eq!(swap_bytes(&swapped_32, ByteSwap::Swap32), raw_save_file);
+  }
+}
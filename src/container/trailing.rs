@@ -0,0 +1,88 @@
+/* Some emulators and flashcart firmwares write more than the flash image into save file:
+   - mGBA appends a 16 bytes RTC footer to save files of games with RTC, like 0x10010 bytes save files.
+   - Some flashcarts always write a save file of the biggest save size, the unused space is padding (0xFF or 0x00).
+   The flash image is at the start of file, the bytes after it are kept as they are. */
+const RTC_FOOTER_SIZE: usize = 0x10;
+const FLASH_IMAGE_SIZES: [usize; 2] = [0x10000, 0x20000];
+
+#[derive(Clone, Copy)]
+pub enum TrailingKind {
+  RtcFooter,
+  Padding,
+  // Only used with "--ignore-trailing".
+  Unknown,
+}
+
+pub struct TrailingData {
+  pub kind: TrailingKind,
+  trailing_bytes: Vec<u8>,
+}
+
+/// The size of flash image, if the bytes after it are a known footer or padding.
+pub fn find_known_trailing_data(file_bytes: &[u8]) -> Option<(TrailingKind, usize)> {
+  if let Some(image_size) = FLASH_IMAGE_SIZES.iter().find(|image_size| file_bytes.len() == *image_size + RTC_FOOTER_SIZE) {
+    return Some((TrailingKind::RtcFooter, *image_size));
+  }
+  let image_size = FLASH_IMAGE_SIZES.iter().find(|image_size| file_bytes.len() > **image_size && file_bytes[**image_size..].iter().all(|byte| *byte == 0xFF || *byte == 0x00))?;
+
+  Some((TrailingKind::Padding, *image_size))
+}
+
+/// The flash image is assumed to be the first 64KB of file if the bytes after it are unknown.
+pub fn find_unknown_trailing_data(file_bytes: &[u8]) -> Option<(TrailingKind, usize)> {
+  (file_bytes.len() > FLASH_IMAGE_SIZES[0]).then_some((TrailingKind::Unknown, FLASH_IMAGE_SIZES[0]))
+}
+
+pub fn unwrap(file_bytes: &[u8], kind: TrailingKind, image_size: usize) -> (TrailingData, Vec<u8>) {
+  (TrailingData { kind, trailing_bytes: file_bytes[image_size..].to_vec() }, file_bytes[..image_size].to_vec())
+}
+
+impl TrailingData {
+  pub fn size(&self) -> usize {
+    self.trailing_bytes.len()
+  }
+}
+
+pub fn wrap(trailing_data: &TrailingData, raw_save_file: &[u8]) -> Vec<u8> {
+  let mut file_bytes = raw_save_file.to_vec();
+  file_bytes.extend_from_slice(&trailing_data.trailing_bytes);
+
+  file_bytes
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rtc_footer_and_padding_are_found() {
+    let file_bytes = vec![0x5Au8; 0x10000 + RTC_FOOTER_SIZE];
+    assert!(matches!(find_known_trailing_data(&file_bytes), Some((TrailingKind::RtcFooter, 0x10000))));
+    let file_bytes = vec![0x5Au8; 0x20000 + RTC_FOOTER_SIZE];
+    assert!(matches!(find_known_trailing_data(&file_bytes), Some((TrailingKind::RtcFooter, 0x20000))));
+
+    let mut file_bytes = vec![0x5Au8; 0x10000];
+    file_bytes.resize(0x80000, 0xFF);
+    assert!(matches!(find_known_trailing_data(&file_bytes), Some((TrailingKind::Padding, 0x10000))));
+  }
+
+  #[test]
+  fn unknown_trailing_data_is_not_known() {
+    let mut file_bytes = vec![0x5Au8; 0x10000];
+    file_bytes.extend_from_slice(&[0xFF, 0x01, 0x00]);
+    assert!(find_known_trailing_data(&file_bytes).is_none());
+    assert!(matches!(find_unknown_trailing_data(&file_bytes), Some((TrailingKind::Unknown, 0x10000))));
+    assert!(find_known_trailing_data(&[0u8; 0x10000]).is_none());
+    assert!(find_unknown_trailing_data(&[0u8; 0x10000]).is_none());
+  }
+
+  #[test]
+  fn unwrap_and_wrap_round_trip() {
+    let mut file_bytes = vec![0x5Au8; 0x10000];
+    file_bytes.extend_from_slice(&[0x01; RTC_FOOTER_SIZE]);
+    let (trailing_data, raw_save_file) = unwrap(&file_bytes, TrailingKind::RtcFooter, 0x10000);
+    assert_eq!(raw_save_file.len(), 0x10000);
+    assert_eq!(trailing_data.size(), RTC_FOOTER_SIZE);
+    assert_eq!(wrap(&trailing_data, &raw_save_file), file_bytes);
+  }
+}
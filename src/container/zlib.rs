@@ -0,0 +1,356 @@
+use crate::hash::adler32;
+
+/* A small zlib (RFC 1950) / deflate (RFC 1951) implementation, based on the structure of zlib's "puff.c".
+   Decompression supports all block types.
+   Compression only writes stored (uncompressed) blocks, which is valid for every zlib reader,
+   save data is small so the size doesn't really matter.
+   It's written here instead of adding a dependency (the tool only depends on clap), it's only used for the save data
+   in mGBA PNG save states, and the output is limited to the size the caller expects, so a small stream can't fill the memory. */
+
+/// Max size of a stored block.
+const MAX_STORED_BLOCK_SIZE: usize = 0xFFFF;
+
+const MAX_CODE_LENGTH: usize = 15;
+
+/// Base lengths and extra bits for length codes 257 - 285.
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA_BITS: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+/// Base distances and extra bits for distance codes 0 - 29.
+const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// The order of code length code lengths in dynamic block header.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompress a zlib stream, a stream that decompresses to more than `max_output_size` bytes is refused.
+pub fn zlib_decompress(data: &[u8], max_output_size: usize) -> Result<Vec<u8>, String> {
+  if data.len() < 6 || data[0] & 0x0F != 8 || (u16::from(data[0]) << 8 | u16::from(data[1])) % 31 != 0 || data[1] & 0x20 != 0 {
+    return Err(String::from("Invalid zlib stream!"));
+  }
+
+  let mut bit_reader = BitReader { data: &data[2..], position: 0, bit_buffer: 0, bit_count: 0 };
+  let output = inflate(&mut bit_reader, max_output_size)?;
+
+  let adler32_position = 2 + bit_reader.position;
+  let Some(adler32_bytes) = data.get(adler32_position..adler32_position + 4) else {
+    return Err(String::from("zlib stream is truncated!"));
+  };
+  if u32::from_be_bytes([adler32_bytes[0], adler32_bytes[1], adler32_bytes[2], adler32_bytes[3]]) != adler32(&output) {
+    return Err(String::from("zlib stream is corrupted!"));
+  }
+
+  Ok(output)
+}
+
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+  // Deflate, 32K window, no preset dictionary, fastest compression.
+  let mut output = vec![0x78, 0x01];
+  let mut chunks = data.chunks(MAX_STORED_BLOCK_SIZE).peekable();
+  if chunks.peek().is_none() {
+    output.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+  }
+  while let Some(chunk) = chunks.next() {
+    output.push(u8::from(chunks.peek().is_none()));
+    output.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+    output.extend_from_slice(&(!(chunk.len() as u16)).to_le_bytes());
+    output.extend_from_slice(chunk);
+  }
+  output.extend_from_slice(&adler32(data).to_be_bytes());
+
+  output
+}
+
+struct BitReader<'a> {
+  data: &'a [u8],
+  // Next byte to read.
+  position: usize,
+  bit_buffer: u32,
+  bit_count: u32,
+}
+
+impl BitReader<'_> {
+  fn read_bits(&mut self, count: u32) -> Result<u32, String> {
+    while self.bit_count < count {
+      let Some(byte) = self.data.get(self.position) else {
+        return Err(String::from("Deflate stream is truncated!"));
+      };
+      self.bit_buffer |= u32::from(*byte) << self.bit_count;
+      self.position += 1;
+      self.bit_count += 8;
+    }
+    let value = self.bit_buffer & ((1u32 << count) - 1);
+    self.bit_buffer >>= count;
+    self.bit_count -= count;
+
+    Ok(value)
+  }
+
+  /// Stored blocks start at byte boundary.
+  fn align_to_byte(&mut self) {
+    self.bit_buffer = 0;
+    self.bit_count = 0;
+  }
+}
+
+/// Canonical Huffman code: the number of codes of each length, and the symbols ordered by code.
+struct Huffman {
+  counts: [u16; MAX_CODE_LENGTH + 1],
+  symbols: Vec<u16>,
+}
+
+impl Huffman {
+  fn new(lengths: &[u8]) -> Result<Self, String> {
+    let mut counts = [0u16; MAX_CODE_LENGTH + 1];
+    for length in lengths {
+      counts[usize::from(*length)] += 1;
+    }
+
+    // Over-subscribed codes are not allowed, incomplete codes are.
+    let mut left = 1i32;
+    for count in &counts[1..] {
+      left = (left << 1) - i32::from(*count);
+      if left < 0 {
+        return Err(String::from("Invalid Huffman code in deflate stream!"));
+      }
+    }
+
+    let mut offsets = [0u16; MAX_CODE_LENGTH + 2];
+    for i in 1..=MAX_CODE_LENGTH {
+      offsets[i + 1] = offsets[i] + counts[i];
+    }
+    let mut symbols = vec![0u16; lengths.len()];
+    for (symbol, length) in lengths.iter().enumerate() {
+      if *length != 0 {
+        symbols[usize::from(offsets[usize::from(*length)])] = symbol as u16;
+        offsets[usize::from(*length)] += 1;
+      }
+    }
+
+    Ok(Self { counts, symbols })
+  }
+
+  fn decode(&self, bit_reader: &mut BitReader) -> Result<u16, String> {
+    let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+    for count in &self.counts[1..] {
+      code |= bit_reader.read_bits(1)? as i32;
+      let count = i32::from(*count);
+      if code - count < first {
+        return Ok(self.symbols[(index + code - first) as usize]);
+      }
+      index += count;
+      first = (first + count) << 1;
+      code <<= 1;
+    }
+
+    Err(String::from("Invalid Huffman code in deflate stream!"))
+  }
+}
+
+fn inflate(bit_reader: &mut BitReader, max_output_size: usize) -> Result<Vec<u8>, String> {
+  let mut output = Vec::new();
+  loop {
+    let is_last_block = bit_reader.read_bits(1)? == 1;
+    match bit_reader.read_bits(2)? {
+      0 => inflate_stored_block(bit_reader, &mut output, max_output_size)?,
+      1 => {
+        let (length_code, distance_code) = get_fixed_codes()?;
+        inflate_codes(bit_reader, &mut output, max_output_size, &length_code, &distance_code)?;
+      }
+      2 => {
+        let (length_code, distance_code) = read_dynamic_codes(bit_reader)?;
+        inflate_codes(bit_reader, &mut output, max_output_size, &length_code, &distance_code)?;
+      }
+      _ => return Err(String::from("Invalid block type in deflate stream!")),
+    }
+    if is_last_block {
+      return Ok(output);
+    }
+  }
+}
+
+fn inflate_stored_block(bit_reader: &mut BitReader, output: &mut Vec<u8>, max_output_size: usize) -> Result<(), String> {
+  bit_reader.align_to_byte();
+  let Some(header) = bit_reader.data.get(bit_reader.position..bit_reader.position + 4) else {
+    return Err(String::from("Deflate stream is truncated!"));
+  };
+  let length = u16::from_le_bytes([header[0], header[1]]);
+  if length != !u16::from_le_bytes([header[2], header[3]]) {
+    return Err(String::from("Invalid stored block in deflate stream!"));
+  }
+  bit_reader.position += 4;
+
+  let Some(bytes) = bit_reader.data.get(bit_reader.position..bit_reader.position + usize::from(length)) else {
+    return Err(String::from("Deflate stream is truncated!"));
+  };
+  check_output_size(output.len() + bytes.len(), max_output_size)?;
+  output.extend_from_slice(bytes);
+  bit_reader.position += usize::from(length);
+
+  Ok(())
+}
+
+fn get_fixed_codes() -> Result<(Huffman, Huffman), String> {
+  let mut lengths = [0u8; 288];
+  lengths[..144].fill(8);
+  lengths[144..256].fill(9);
+  lengths[256..280].fill(7);
+  lengths[280..].fill(8);
+
+  Ok((Huffman::new(&lengths)?, Huffman::new(&[5u8; 30])?))
+}
+
+fn read_dynamic_codes(bit_reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+  let length_count = bit_reader.read_bits(5)? as usize + 257;
+  let distance_count = bit_reader.read_bits(5)? as usize + 1;
+  let code_length_count = bit_reader.read_bits(4)? as usize + 4;
+  if length_count > 286 || distance_count > 30 {
+    return Err(String::from("Invalid dynamic block in deflate stream!"));
+  }
+
+  let mut code_lengths = [0u8; 19];
+  for i in 0..code_length_count {
+    code_lengths[CODE_LENGTH_ORDER[i]] = bit_reader.read_bits(3)? as u8;
+  }
+  let code_length_code = Huffman::new(&code_lengths)?;
+
+  let mut lengths = vec![0u8; length_count + distance_count];
+  let mut i = 0;
+  while i < lengths.len() {
+    let symbol = code_length_code.decode(bit_reader)?;
+    let (value, repeat_count) = match symbol {
+      0..=15 => (symbol as u8, 1),
+      16 => {
+        if i == 0 {
+          return Err(String::from("Invalid dynamic block in deflate stream!"));
+        }
+        (lengths[i - 1], 3 + bit_reader.read_bits(2)? as usize)
+      }
+      17 => (0, 3 + bit_reader.read_bits(3)? as usize),
+      _ => (0, 11 + bit_reader.read_bits(7)? as usize),
+    };
+    if i + repeat_count > lengths.len() {
+      return Err(String::from("Invalid dynamic block in deflate stream!"));
+    }
+    lengths[i..i + repeat_count].fill(value);
+    i += repeat_count;
+  }
+  if lengths[256] == 0 {
+    return Err(String::from("Invalid dynamic block in deflate stream!"));
+  }
+
+  Ok((Huffman::new(&lengths[..length_count])?, Huffman::new(&lengths[length_count..])?))
+}
+
+fn inflate_codes(bit_reader: &mut BitReader, output: &mut Vec<u8>, max_output_size: usize, length_code: &Huffman, distance_code: &Huffman) -> Result<(), String> {
+  loop {
+    let symbol = usize::from(length_code.decode(bit_reader)?);
+    if symbol < 256 {
+      check_output_size(output.len() + 1, max_output_size)?;
+      output.push(symbol as u8);
+      continue;
+    }
+    if symbol == 256 {
+      return Ok(());
+    }
+
+    let Some(length_base) = LENGTH_BASE.get(symbol - 257) else {
+      return Err(String::from("Invalid length code in deflate stream!"));
+    };
+    let length = usize::from(*length_base) + bit_reader.read_bits(u32::from(LENGTH_EXTRA_BITS[symbol - 257]))? as usize;
+    let distance_symbol = usize::from(distance_code.decode(bit_reader)?);
+    let Some(distance_base) = DISTANCE_BASE.get(distance_symbol) else {
+      return Err(String::from("Invalid distance code in deflate stream!"));
+    };
+    let distance = usize::from(*distance_base) + bit_reader.read_bits(u32::from(DISTANCE_EXTRA_BITS[distance_symbol]))? as usize;
+    if distance > output.len() {
+      return Err(String::from("Invalid distance in deflate stream!"));
+    }
+
+    check_output_size(output.len() + length, max_output_size)?;
+
+    // The copy may overlap itself.
+    let start = output.len() - distance;
+    for j in 0..length {
+      output.push(output[start + j]);
+    }
+  }
+}
+
+fn check_output_size(output_size: usize, max_output_size: usize) -> Result<(), String> {
+  if output_size > max_output_size {
+    return Err(format!("Deflate stream is larger than {max_output_size:#X} bytes!"));
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::field::from_hex_string;
+
+  const MAX_TEST_OUTPUT_SIZE: usize = 0x2_0000;
+
+  // Streams created by zlib 1.x: level 0 for the stored block, level 9 for the others.
+  const STORED_STREAM: &str = "7801010a00f5ff476f6c64656e2053756e142803b0";
+  const FIXED_STREAM: &str = "78da7376f475f5f10f517046a5014ae10650";
+  const DYNAMIC_STREAM: &str = "78daedd0cb0980301004d056a604ff25281104c10a46593020392422966f0d7395bdbfd30b853c3031db8df0306189c468577c315b4ac476da4eacd1724185a0f05ae38dc65b8d771aef353e68dc233dd2233dd223ff1af9012b5a6f8a";
+
+  fn get_dynamic_stream_text() -> Vec<u8> {
+    (0..40).flat_map(|i| format!("Isaac Garet Ivan Mia Felix Jenna Sheba Piers {} ", i % 7).into_bytes()).collect()
+  }
+
+  #[test]
+  fn decompress_stored_block() {
+    assert_eq!(zlib_decompress(&from_hex_string(STORED_STREAM).unwrap(), MAX_TEST_OUTPUT_SIZE).unwrap(), b"Golden Sun");
+  }
+
+  #[test]
+  fn decompress_fixed_block() {
+    assert_eq!(zlib_decompress(&from_hex_string(FIXED_STREAM).unwrap(), MAX_TEST_OUTPUT_SIZE).unwrap(), b"CAMELOT CAMELOT CAMELOT");
+  }
+
+  #[test]
+  fn decompress_dynamic_block() {
+    assert_eq!(zlib_decompress(&from_hex_string(DYNAMIC_STREAM).unwrap(), MAX_TEST_OUTPUT_SIZE).unwrap(), get_dynamic_stream_text());
+  }
+
+  #[test]
+  fn adler32_mismatch_is_refused() {
+    let mut stream = from_hex_string(FIXED_STREAM).unwrap();
+    let last_index = stream.len() - 1;
+    stream[last_index] ^= 0x01;
+    assert!(zlib_decompress(&stream, MAX_TEST_OUTPUT_SIZE).is_err());
+  }
+
+  #[test]
+  fn malformed_streams_are_refused() {
+    let stream = from_hex_string(DYNAMIC_STREAM).unwrap();
+    assert!(zlib_decompress(&stream[..stream.len() - 5], MAX_TEST_OUTPUT_SIZE).is_err());
+    assert!(zlib_decompress(&stream[..40], MAX_TEST_OUTPUT_SIZE).is_err());
+    // Wrong compression method, and a header check that isn't a multiple of 31.
+    assert!(zlib_decompress(&[0x79, 0xDA, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01], MAX_TEST_OUTPUT_SIZE).is_err());
+    assert!(zlib_decompress(&[0x78, 0xDB, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01], MAX_TEST_OUTPUT_SIZE).is_err());
+    // Reserved block type 3.
+    assert!(zlib_decompress(&[0x78, 0x01, 0x07, 0x00, 0x00, 0x00, 0x00, 0x01], MAX_TEST_OUTPUT_SIZE).is_err());
+    // Stored block length doesn't match its one's complement.
+    assert!(zlib_decompress(&[0x78, 0x01, 0x01, 0x01, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x01], MAX_TEST_OUTPUT_SIZE).is_err());
+  }
+
+  #[test]
+  fn output_size_is_limited() {
+    // 0x30000 zero bytes, like a crafted save state chunk.
+    let stream = from_hex_string(&format!("78DAEDC13101000000C2A0F54FED6909A0{}E006002D0001", "00".repeat(190))).unwrap();
+    assert_eq!(zlib_decompress(&stream, 0x30000).unwrap().len(), 0x30000);
+    assert_eq!(zlib_decompress(&stream, 0x2FFFF), Err(String::from("Deflate stream is larger than 0x2FFFF bytes!")));
+    let stream = zlib_compress(&[0x42; 0x100]);
+    assert!(zlib_decompress(&stream, 0x100).is_ok());
+    assert!(zlib_decompress(&stream, 0xFF).is_err());
+  }
+
+  #[test]
+  fn compress_round_trip() {
+    for data in [Vec::new(), get_dynamic_stream_text(), (0..0x1_2345).map(|i| (i % 253) as u8).collect()] {
+      assert_eq!(zlib_decompress(&zlib_compress(&data), MAX_TEST_OUTPUT_SIZE).unwrap(), data);
+    }
+  }
+}
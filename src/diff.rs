@@ -0,0 +1,185 @@
+use std::fmt::Write;
+use std::ops::Range;
+use crate::SAVE_SLOT_SIZE;
+use crate::field::{describe_field_value, get_changed_ranges, get_offset_label, get_save_fields};
+use crate::save::{get_live_blocks, get_live_second_half_blocks, SaveBlock};
+use crate::MAX_VALID_SLOT_NUMBER;
+
+const GAME_TYPE_LABEL: [&str; 2] = ["Golden Sun", "Golden Sun: The Lost Age"];
+
+/// Compare two save files slot by slot and field by field.
+/// Only the block used by game (the one with the highest priority) of each slot is compared.
+/// The second half of a TBS slot has no known fields, so it is only compared byte by byte.
+pub fn get_diff_report(raw_save_file_a: &[u8], game_type_index_a: usize, raw_save_file_b: &[u8], game_type_index_b: usize) -> String {
+  let mut report = String::new();
+  if game_type_index_a != game_type_index_b {
+    writeln!(report, "The save files are from different games: A is {}, B is {}.", GAME_TYPE_LABEL[game_type_index_a], GAME_TYPE_LABEL[game_type_index_b]).unwrap();
+    return report;
+  }
+  let game_type_index = game_type_index_a;
+  if raw_save_file_a.len() != raw_save_file_b.len() {
+    writeln!(report, "File size: {:#X} -> {:#X}", raw_save_file_a.len(), raw_save_file_b.len()).unwrap();
+  }
+
+  let live_blocks_a = get_live_blocks(raw_save_file_a, game_type_index);
+  let live_blocks_b = get_live_blocks(raw_save_file_b, game_type_index);
+  let mut slot_numbers: Vec<u8> = live_blocks_a.iter().chain(live_blocks_b.iter()).map(|live_block| live_block.slot_number).collect();
+  slot_numbers.sort_unstable();
+  slot_numbers.dedup();
+
+  let save_fields = get_save_fields(game_type_index);
+  let mut compared_ranges_a = Vec::new();
+  let mut compared_ranges_b = Vec::new();
+  for slot_number in slot_numbers {
+    let live_block_a_option = live_blocks_a.iter().find(|live_block| live_block.slot_number == slot_number);
+    let live_block_b_option = live_blocks_b.iter().find(|live_block| live_block.slot_number == slot_number);
+    let (live_block_a, live_block_b) = match (live_block_a_option, live_block_b_option) {
+      (Some(live_block_a), Some(live_block_b)) => (live_block_a, live_block_b),
+      (Some(live_block_a), None) => {
+        writeln!(report, "Slot {slot_number}: only in A (block {})", live_block_a.index).unwrap();
+        continue;
+      }
+      (None, Some(live_block_b)) => {
+        writeln!(report, "Slot {slot_number}: only in B (block {})", live_block_b.index).unwrap();
+        continue;
+      }
+      (None, None) => continue,
+    };
+
+    let block_range_a = get_block_range(live_block_a, game_type_index);
+    let block_range_b = get_block_range(live_block_b, game_type_index);
+    compared_ranges_a.push(block_range_a.clone());
+    compared_ranges_b.push(block_range_b.clone());
+    let raw_block_a = &raw_save_file_a[block_range_a];
+    let raw_block_b = &raw_save_file_b[block_range_b];
+    if raw_block_a == raw_block_b {
+      writeln!(report, "Slot {slot_number}: identical").unwrap();
+      continue;
+    }
+
+    writeln!(report, "Slot {slot_number} (A: block {}, B: block {}):", live_block_a.index, live_block_b.index).unwrap();
+    let mut is_known_byte = vec![false; raw_block_a.len()];
+    for save_field in &save_fields {
+      let field_range = save_field.offset..save_field.offset + save_field.size;
+      is_known_byte[field_range.clone()].fill(true);
+      if raw_block_a[field_range.clone()] == raw_block_b[field_range.clone()] {
+        continue;
+      }
+      writeln!(report, "  {:<18}{} -> {}", format!("{}:", save_field.name), describe_field_value(save_field, &raw_block_a[field_range.clone()], game_type_index), describe_field_value(save_field, &raw_block_b[field_range], game_type_index)).unwrap();
+    }
+    if live_block_a.is_checksum_valid != live_block_b.is_checksum_valid {
+      writeln!(report, "  {:<18}{} -> {}", "Checksum status:", get_checksum_status(live_block_a), get_checksum_status(live_block_b)).unwrap();
+    }
+
+    // Bytes outside known fields.
+    let unknown_ranges: Vec<Range<usize>> = get_changed_ranges(raw_block_a, raw_block_b).into_iter()
+      .flat_map(|changed_range| split_by_known_bytes(changed_range, &is_known_byte))
+      .collect();
+    if !unknown_ranges.is_empty() {
+      writeln!(report, "  Other bytes ({} range(s), block offset):", unknown_ranges.len()).unwrap();
+      for unknown_range in unknown_ranges {
+        writeln!(report, "    {:#06X}-{:#06X} ({} byte(s), {})", unknown_range.start, unknown_range.end - 1, unknown_range.len(), get_offset_label(unknown_range.start, game_type_index)).unwrap();
+      }
+    }
+  }
+
+  let second_half_blocks_a = get_live_second_half_blocks(raw_save_file_a, game_type_index);
+  let second_half_blocks_b = get_live_second_half_blocks(raw_save_file_b, game_type_index);
+  let mut second_half_slot_numbers: Vec<u8> = second_half_blocks_a.iter().chain(second_half_blocks_b.iter()).map(|second_half_block| second_half_block.slot_number).collect();
+  second_half_slot_numbers.sort_unstable();
+  second_half_slot_numbers.dedup();
+  for second_half_slot_number in second_half_slot_numbers {
+    let slot_number = second_half_slot_number - (MAX_VALID_SLOT_NUMBER + 1);
+    let second_half_block_a_option = second_half_blocks_a.iter().find(|second_half_block| second_half_block.slot_number == second_half_slot_number);
+    let second_half_block_b_option = second_half_blocks_b.iter().find(|second_half_block| second_half_block.slot_number == second_half_slot_number);
+    let (second_half_block_a, second_half_block_b) = match (second_half_block_a_option, second_half_block_b_option) {
+      (Some(second_half_block_a), Some(second_half_block_b)) => (second_half_block_a, second_half_block_b),
+      (Some(second_half_block_a), None) => {
+        writeln!(report, "Slot {slot_number} second half: only in A (block {})", second_half_block_a.index).unwrap();
+        continue;
+      }
+      (None, Some(second_half_block_b)) => {
+        writeln!(report, "Slot {slot_number} second half: only in B (block {})", second_half_block_b.index).unwrap();
+        continue;
+      }
+      (None, None) => continue,
+    };
+
+    let block_range_a = get_block_range(second_half_block_a, game_type_index);
+    let block_range_b = get_block_range(second_half_block_b, game_type_index);
+    compared_ranges_a.push(block_range_a.clone());
+    compared_ranges_b.push(block_range_b.clone());
+    let changed_ranges = get_changed_ranges(&raw_save_file_a[block_range_a], &raw_save_file_b[block_range_b]);
+    if changed_ranges.is_empty() {
+      writeln!(report, "Slot {slot_number} second half: identical").unwrap();
+      continue;
+    }
+    let byte_count: usize = changed_ranges.iter().map(ExactSizeIterator::len).sum();
+    writeln!(report, "Slot {slot_number} second half (A: block {}, B: block {}): {byte_count} byte(s) in {} range(s), from {:#06X} to {:#06X} (block offset)", second_half_block_a.index, second_half_block_b.index, changed_ranges.len(), changed_ranges[0].start, changed_ranges[changed_ranges.len() - 1].end - 1).unwrap();
+    if second_half_block_a.is_checksum_valid != second_half_block_b.is_checksum_valid {
+      writeln!(report, "  {:<18}{} -> {}", "Checksum status:", get_checksum_status(second_half_block_a), get_checksum_status(second_half_block_b)).unwrap();
+    }
+  }
+
+  // Old blocks, empty blocks and everything else that is not used by game.
+  let common_length = raw_save_file_a.len().min(raw_save_file_b.len());
+  let compared_ranges: Vec<Range<usize>> = compared_ranges_a.into_iter().chain(compared_ranges_b).collect();
+  let outside_ranges = get_outside_ranges(get_changed_ranges(&raw_save_file_a[..common_length], &raw_save_file_b[..common_length]), &compared_ranges, common_length);
+  if !outside_ranges.is_empty() {
+    let byte_count: usize = outside_ranges.iter().map(ExactSizeIterator::len).sum();
+    writeln!(report, "Outside live save data: {byte_count} byte(s) in {} range(s), from {:#07X} to {:#07X} (file offset)", outside_ranges.len(), outside_ranges[0].start, outside_ranges[outside_ranges.len() - 1].end - 1).unwrap();
+  }
+
+  report
+}
+
+fn get_block_range(save_block: &SaveBlock, game_type_index: usize) -> Range<usize> {
+  save_block.start(game_type_index)..save_block.start(game_type_index) + SAVE_SLOT_SIZE[game_type_index]
+}
+
+fn get_checksum_status(save_block: &SaveBlock) -> &'static str {
+  if save_block.is_checksum_valid { "valid" } else { "invalid" }
+}
+
+/// Remove the compared bytes from changed ranges, a changed range may start or end inside a compared range, or cover one.
+fn get_outside_ranges(changed_ranges: Vec<Range<usize>>, compared_ranges: &[Range<usize>], length: usize) -> Vec<Range<usize>> {
+  let mut is_compared_byte = vec![false; length];
+  for compared_range in compared_ranges {
+    is_compared_byte[compared_range.start.min(length)..compared_range.end.min(length)].fill(true);
+  }
+
+  changed_ranges.into_iter().flat_map(|changed_range| split_by_known_bytes(changed_range, &is_compared_byte)).collect()
+}
+
+/// Remove bytes of known fields from a range, which may split it into several ranges.
+fn split_by_known_bytes(changed_range: Range<usize>, is_known_byte: &[bool]) -> Vec<Range<usize>> {
+  let mut unknown_ranges: Vec<Range<usize>> = Vec::new();
+  for i in changed_range {
+    if is_known_byte[i] {
+      continue;
+    }
+    match unknown_ranges.last_mut() {
+      Some(last_range) if last_range.end == i => last_range.end = i + 1,
+      _ => unknown_ranges.push(i..i + 1),
+    }
+  }
+
+  unknown_ranges
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  #[allow(clippy::single_range_in_vec_init)]
+  fn changed_ranges_are_clipped_to_compared_ranges() {
+    let compared_ranges = [0x1000..0x2000, 0x3000..0x4000];
+    assert_eq!(get_outside_ranges(vec![0x1800..0x2010], &compared_ranges, 0x5000), vec![0x2000..0x2010]);
+    assert_eq!(get_outside_ranges(vec![0x0FF0..0x1010], &compared_ranges, 0x5000), vec![0x0FF0..0x1000]);
+    assert_eq!(get_outside_ranges(vec![0x0F00..0x4100], &compared_ranges, 0x5000), vec![0x0F00..0x1000, 0x2000..0x3000, 0x4000..0x4100]);
+    assert_eq!(get_outside_ranges(vec![0x1100..0x1200, 0x4800..0x4801], &compared_ranges, 0x5000), vec![0x4800..0x4801]);
+    // A compared range past the end of a shorter file.
+    assert_eq!(get_outside_ranges(vec![0x2F00..0x3000], &[0x3000..0x4000], 0x3000), vec![0x2F00..0x3000]);
+  }
+}
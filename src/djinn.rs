@@ -0,0 +1,210 @@
+use std::fmt::Write;
+use crate::character::{find_character, get_character_offset, ELEMENT_LABEL};
+use crate::field::PC_LABEL;
+use crate::PARTY_MEMBERS_COUNT;
+
+/// TBS has 7 djinn of each element, TLA has 18, the first 7 are the ones from TBS.
+pub const DJINN_COUNT_PER_ELEMENT: [usize; 2] = [7, 18];
+
+/// Djinn's English names, in the order of bits in character data.
+pub const DJINN_NAME: [[&str; 18]; 4] = [
+  ["Flint", "Granite", "Quartz", "Vine", "Sap", "Ground", "Bane", "Echo", "Iron", "Steel", "Mud", "Flower", "Meld", "Petra", "Salt", "Geode", "Mold", "Crystal"],
+  ["Fizz", "Sleet", "Mist", "Spritz", "Hail", "Tonic", "Dew", "Fog", "Sour", "Spring", "Shade", "Chill", "Steam", "Rime", "Gel", "Eddy", "Balm", "Serac"],
+  ["Forge", "Fever", "Corona", "Scorch", "Ember", "Flash", "Torch", "Cannon", "Spark", "Kindle", "Char", "Coal", "Reflux", "Core", "Tinder", "Shine", "Fury", "Fugue"],
+  ["Gust", "Breeze", "Zephyr", "Smog", "Kite", "Squall", "Luff", "Breath", "Blitz", "Ether", "Waft", "Haze", "Wheeze", "Aroma", "Whorl", "Gasp", "Lull", "Gale"],
+];
+
+/* Djinn in character data, one u32 bitfield for each element (Venus, Mercury, Mars, Jupiter), bit N is the Nth djinni.
+   0xF8   Owned djinn
+   0x108  Set djinn, a djinni that is owned but not set is on standby
+   0x118  Number of owned djinn of each element (u8)
+   0x11C  Number of set djinn of each element (u8) */
+const DJINN_OWNED_OFFSET: usize = 0xF8;
+const DJINN_SET_OFFSET: usize = 0x108;
+const DJINN_OWNED_COUNT_OFFSET: usize = 0x118;
+const DJINN_SET_COUNT_OFFSET: usize = 0x11C;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DjinnState {
+  Set,
+  Standby,
+}
+
+impl DjinnState {
+  fn label(self) -> &'static str {
+    match self {
+      Self::Set => "set",
+      Self::Standby => "standby",
+    }
+  }
+}
+
+/// A djinni is identified by its element and its index in that element.
+#[derive(Clone, Copy)]
+pub struct Djinni {
+  pub element_index: usize,
+  pub index: usize,
+}
+
+impl Djinni {
+  pub fn name(self) -> &'static str {
+    DJINN_NAME[self.element_index][self.index]
+  }
+}
+
+/// Find a djinni by its English name, case insensitive.
+pub fn find_djinni(djinni_name: &str, game_type_index: usize) -> Option<Djinni> {
+  (0..ELEMENT_LABEL.len()).find_map(|element_index| {
+    DJINN_NAME[element_index][..DJINN_COUNT_PER_ELEMENT[game_type_index]].iter()
+      .position(|name| name.eq_ignore_ascii_case(djinni_name))
+      .map(|index| Djinni { element_index, index })
+  })
+}
+
+fn read_bitfield(raw_block: &[u8], pc_index: usize, offset: usize, element_index: usize, game_type_index: usize) -> u32 {
+  let location_index = get_character_offset(pc_index, game_type_index) + offset + element_index * 4;
+  u32::from_le_bytes([raw_block[location_index], raw_block[location_index + 1], raw_block[location_index + 2], raw_block[location_index + 3]])
+}
+
+fn write_bitfield(raw_block: &mut [u8], pc_index: usize, offset: usize, element_index: usize, value: u32, game_type_index: usize) {
+  let location_index = get_character_offset(pc_index, game_type_index) + offset + element_index * 4;
+  raw_block[location_index..location_index + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// The state of a djinni held by a character, `None` if the character doesn't have it.
+pub fn get_djinni_state(raw_block: &[u8], pc_index: usize, djinni: Djinni, game_type_index: usize) -> Option<DjinnState> {
+  let bit = 1u32 << djinni.index;
+  if read_bitfield(raw_block, pc_index, DJINN_OWNED_OFFSET, djinni.element_index, game_type_index) & bit == 0 {
+    None
+  } else if read_bitfield(raw_block, pc_index, DJINN_SET_OFFSET, djinni.element_index, game_type_index) & bit != 0 {
+    Some(DjinnState::Set)
+  } else {
+    Some(DjinnState::Standby)
+  }
+}
+
+/// Give a djinni to a character with a state, or take it away with `None`, the djinn counts are updated too.
+pub fn set_djinni_state(raw_block: &mut [u8], pc_index: usize, djinni: Djinni, djinn_state_option: Option<DjinnState>, game_type_index: usize) {
+  let bit = 1u32 << djinni.index;
+  let mut owned_bitfield = read_bitfield(raw_block, pc_index, DJINN_OWNED_OFFSET, djinni.element_index, game_type_index) & !bit;
+  let mut set_bitfield = read_bitfield(raw_block, pc_index, DJINN_SET_OFFSET, djinni.element_index, game_type_index) & !bit;
+  if let Some(djinn_state) = djinn_state_option {
+    owned_bitfield |= bit;
+    if djinn_state == DjinnState::Set {
+      set_bitfield |= bit;
+    }
+  }
+  write_bitfield(raw_block, pc_index, DJINN_OWNED_OFFSET, djinni.element_index, owned_bitfield, game_type_index);
+  write_bitfield(raw_block, pc_index, DJINN_SET_OFFSET, djinni.element_index, set_bitfield, game_type_index);
+
+  let character_offset = get_character_offset(pc_index, game_type_index);
+  raw_block[character_offset + DJINN_OWNED_COUNT_OFFSET + djinni.element_index] = owned_bitfield.count_ones() as u8;
+  raw_block[character_offset + DJINN_SET_COUNT_OFFSET + djinni.element_index] = set_bitfield.count_ones() as u8;
+}
+
+/// Djinn set by a character.
+pub fn get_set_djinn(raw_block: &[u8], pc_index: usize, game_type_index: usize) -> Vec<Djinni> {
+  (0..ELEMENT_LABEL.len())
+    .flat_map(|element_index| (0..DJINN_COUNT_PER_ELEMENT[game_type_index]).map(move |index| Djinni { element_index, index }))
+    .filter(|djinni| get_djinni_state(raw_block, pc_index, *djinni, game_type_index) == Some(DjinnState::Set))
+    .collect()
+}
+
+/// The number of set djinn of each element (Venus, Mercury, Mars, Jupiter), each of them adds 1 to the elemental level.
+pub fn get_set_djinn_counts(raw_block: &[u8], pc_index: usize, game_type_index: usize) -> [u8; 4] {
+  std::array::from_fn(|element_index| read_bitfield(raw_block, pc_index, DJINN_SET_OFFSET, element_index, game_type_index).count_ones() as u8)
+}
+
+/// All characters who have this djinni, more than one means the save data is broken.
+pub fn get_djinni_owners(raw_block: &[u8], djinni: Djinni, game_type_index: usize) -> Vec<(usize, DjinnState)> {
+  (0..PARTY_MEMBERS_COUNT[game_type_index])
+    .filter_map(|pc_index| get_djinni_state(raw_block, pc_index, djinni, game_type_index).map(|djinn_state| (pc_index, djinn_state)))
+    .collect()
+}
+
+/// "<character>.djinn.<djinni>=set|standby|none": give a djinni nobody has to a character, change its state, or take it away.
+pub fn assign_character_djinni(raw_block: &mut [u8], pc_index: usize, djinni_name: &str, value: &str, game_type_index: usize) -> Result<String, String> {
+  let djinni = find_djinni(djinni_name, game_type_index).ok_or_else(|| format!("Unknown djinni \"{djinni_name}\" in this game!"))?;
+  let djinn_state_option = match value.to_ascii_lowercase().as_str() {
+    "set" => Some(DjinnState::Set),
+    "standby" => Some(DjinnState::Standby),
+    "none" => None,
+    _ => return Err(format!("Invalid djinni state \"{value}\", it should be \"set\", \"standby\" or \"none\"!")),
+  };
+  if let Some((owner_pc_index, _)) = get_djinni_owners(raw_block, djinni, game_type_index).into_iter().find(|(owner_pc_index, _)| *owner_pc_index != pc_index) {
+    if djinn_state_option.is_some() {
+      return Err(format!("{} is held by {}, use \"djinn.{}={}\" to move it!", djinni.name(), PC_LABEL[owner_pc_index], djinni.name().to_lowercase(), PC_LABEL[pc_index].to_lowercase()));
+    }
+  }
+
+  let old_state_option = get_djinni_state(raw_block, pc_index, djinni, game_type_index);
+  set_djinni_state(raw_block, pc_index, djinni, djinn_state_option, game_type_index);
+
+  Ok(format!("{}'s {}: {} -> {}", PC_LABEL[pc_index], djinni.name(), describe_djinni_state(old_state_option), describe_djinni_state(djinn_state_option)))
+}
+
+/// "djinn.<djinni>=<character>": move a djinni to another character, it keeps its state, a new djinni is on standby.
+pub fn move_djinni(raw_block: &mut [u8], djinni_name: &str, character_name: &str, game_type_index: usize) -> Result<String, String> {
+  let djinni = find_djinni(djinni_name, game_type_index).ok_or_else(|| format!("Unknown djinni \"{djinni_name}\" in this game!"))?;
+  let pc_index = find_character(character_name, game_type_index).ok_or_else(|| format!("Unknown character \"{character_name}\"!"))?;
+
+  let owners = get_djinni_owners(raw_block, djinni, game_type_index);
+  let djinn_state = owners.first().map_or(DjinnState::Standby, |(_, djinn_state)| *djinn_state);
+  for (owner_pc_index, _) in &owners {
+    set_djinni_state(raw_block, *owner_pc_index, djinni, None, game_type_index);
+  }
+  set_djinni_state(raw_block, pc_index, djinni, Some(djinn_state), game_type_index);
+
+  let old_owner = if owners.is_empty() { String::from("nobody") } else { owners.iter().map(|(owner_pc_index, _)| PC_LABEL[*owner_pc_index]).collect::<Vec<&str>>().join(", ") };
+  Ok(format!("{}: {old_owner} -> {} ({})", djinni.name(), PC_LABEL[pc_index], djinn_state.label()))
+}
+
+/// Reject djinn states the game can't have: a djinni held by more than one character, a set djinni that is not owned, or djinn that don't exist in this game.
+pub fn validate_djinn(raw_block: &[u8], game_type_index: usize) -> Result<(), String> {
+  let valid_bits = (1u32 << DJINN_COUNT_PER_ELEMENT[game_type_index]) - 1;
+  for element_index in 0..ELEMENT_LABEL.len() {
+    let mut owned_by_anyone = 0u32;
+    for (pc_index, pc_label) in PC_LABEL.iter().enumerate().take(PARTY_MEMBERS_COUNT[game_type_index]) {
+      let owned_bitfield = read_bitfield(raw_block, pc_index, DJINN_OWNED_OFFSET, element_index, game_type_index);
+      let set_bitfield = read_bitfield(raw_block, pc_index, DJINN_SET_OFFSET, element_index, game_type_index);
+      if (owned_bitfield | set_bitfield) & !valid_bits != 0 {
+        return Err(format!("{pc_label} has {} djinn that don't exist in this game!", ELEMENT_LABEL[element_index]));
+      }
+      if set_bitfield & !owned_bitfield != 0 {
+        return Err(format!("{pc_label} has set {} djinn that are not owned!", ELEMENT_LABEL[element_index]));
+      }
+      let shared_bitfield = owned_by_anyone & owned_bitfield;
+      if shared_bitfield != 0 {
+        return Err(format!("{} is held by more than one character!", DJINN_NAME[element_index][shared_bitfield.trailing_zeros() as usize]));
+      }
+      owned_by_anyone |= owned_bitfield;
+    }
+  }
+
+  Ok(())
+}
+
+fn describe_djinni_state(djinn_state_option: Option<DjinnState>) -> &'static str {
+  djinn_state_option.map_or("none", DjinnState::label)
+}
+
+/// Djinn held by a character, like "Flint (set), Echo (standby)".
+pub fn describe_character_djinn(raw_block: &[u8], pc_index: usize, game_type_index: usize) -> String {
+  let mut description = String::new();
+  for element_index in 0..ELEMENT_LABEL.len() {
+    for index in 0..DJINN_COUNT_PER_ELEMENT[game_type_index] {
+      let djinni = Djinni { element_index, index };
+      if let Some(djinn_state) = get_djinni_state(raw_block, pc_index, djinni, game_type_index) {
+        if !description.is_empty() {
+          description.push_str(", ");
+        }
+        write!(description, "{} ({})", djinni.name(), djinn_state.label()).unwrap();
+      }
+    }
+  }
+  if description.is_empty() {
+    description.push_str("none");
+  }
+
+  description
+}
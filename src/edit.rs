@@ -0,0 +1,125 @@
+use std::ops::Range;
+use crate::character::{find_character, find_character_field, read_character_field, write_character_field, CHARACTER_FIELDS};
+use crate::class::{compute_class_id, compute_elemental_levels, get_character_class_id};
+use crate::djinn::{assign_character_djinni, move_djinni, validate_djinn};
+use crate::field::PC_LABEL;
+use crate::gamedata::GameData;
+use crate::inventory::assign_character_item;
+use crate::location::assign_location;
+use crate::party::assign_party;
+use crate::psynergy::assign_character_psynergy;
+use crate::save::{get_live_blocks, update_checksum};
+use crate::status::{assign_status_field, find_status_field, STATUS_FIELDS};
+use crate::{PARTY_MEMBERS_COUNT, SAVE_SLOT_SIZE};
+
+/// Get the block used by game for a slot.
+/// The slot can be omitted if there is only one slot in save file.
+pub fn get_slot_block_range(raw_save_file: &[u8], game_type_index: usize, slot_number_option: Option<u8>) -> Result<Range<usize>, String> {
+  let live_blocks = get_live_blocks(raw_save_file, game_type_index);
+  let live_block = match slot_number_option {
+    Some(slot_number) => live_blocks.iter().find(|live_block| live_block.slot_number == slot_number).ok_or_else(|| format!("There is no save data in slot {slot_number}!"))?,
+    None if live_blocks.len() == 1 => &live_blocks[0],
+    None => return Err(format!("There are {} save slots in this save file, please choose one with \"--slot\"!", live_blocks.len())),
+  };
+  let block_start = live_block.start(game_type_index);
+
+  Ok(block_start..block_start + SAVE_SLOT_SIZE[game_type_index])
+}
+
+/// Apply "<target>=<value>" assignments to a save data block, then recalculate its checksum.
+/// Returns a line for each change, like "Felix's level: 30 -> 40", and a line for each computed class that changes.
+pub fn apply_assignments(raw_block: &mut [u8], assignments: &[&String], game_type_index: usize, game_data: &GameData) -> Result<Vec<String>, String> {
+  let pc_count = PARTY_MEMBERS_COUNT[game_type_index];
+  let old_class_ids: Vec<Result<u8, String>> = (0..pc_count).map(|pc_index| compute_class_id(raw_block, pc_index, game_type_index, game_data)).collect();
+  let old_elemental_levels: Vec<[u32; 4]> = (0..pc_count).map(|pc_index| compute_elemental_levels(raw_block, pc_index, game_type_index)).collect();
+  let mut change_descriptions = Vec::new();
+  for assignment in assignments {
+    let Some((target, value)) = assignment.split_once('=') else {
+      return Err(format!("Invalid assignment \"{assignment}\", it should be like \"felix.level=40\"!"));
+    };
+    change_descriptions.push(apply_assignment(raw_block, target.trim(), value.trim(), game_type_index, game_data).map_err(|error_message| format!("\"{assignment}\": {error_message}"))?);
+  }
+  // The stored class is not changed, the game changes it when djinn or elemental levels change in game.
+  // For characters whose elemental levels changed, the class is shown even if it's the same, or why it can't be computed.
+  let mut unchecked_messages: Vec<String> = Vec::new();
+  for (pc_index, old_class_id) in old_class_ids.iter().enumerate() {
+    match (old_class_id, compute_class_id(raw_block, pc_index, game_type_index, game_data)) {
+      (Ok(old_class_id), Ok(new_class_id)) if *old_class_id != new_class_id => {
+        change_descriptions.push(format!("{}'s computed class: {} -> {} (stored class: {})", PC_LABEL[pc_index], game_data.get_class_name(*old_class_id), game_data.get_class_name(new_class_id), game_data.get_class_name(get_character_class_id(raw_block, pc_index, game_type_index))));
+      }
+      (Ok(_), Ok(new_class_id)) if compute_elemental_levels(raw_block, pc_index, game_type_index) != old_elemental_levels[pc_index] => {
+        change_descriptions.push(format!("{}'s computed class: {} (unchanged)", PC_LABEL[pc_index], game_data.get_class_name(new_class_id)));
+      }
+      (_, Err(error_message)) if compute_elemental_levels(raw_block, pc_index, game_type_index) != old_elemental_levels[pc_index] && !unchecked_messages.contains(&error_message) => {
+        change_descriptions.push(format!("Note: {error_message}"));
+        unchecked_messages.push(error_message);
+      }
+      _ => {}
+    }
+  }
+  update_checksum(raw_block, game_type_index);
+
+  Ok(change_descriptions)
+}
+
+fn apply_assignment(raw_block: &mut [u8], target: &str, value: &str, game_type_index: usize, game_data: &GameData) -> Result<String, String> {
+  let target_parts: Vec<&str> = target.split('.').collect();
+  match target_parts[..] {
+    ["location"] => assign_location(raw_block, None, value, game_type_index, game_data),
+    ["location", part_key] => assign_location(raw_block, Some(part_key), value, game_type_index, game_data),
+    ["party"] => assign_party(raw_block, None, value, game_type_index),
+    [key] => {
+      let Some(status_field) = find_status_field(key) else {
+        return Err(format!("Unknown target \"{key}\", it should be one of {}, party, location or <character>.<field>!", STATUS_FIELDS.iter().map(|status_field| status_field.key).collect::<Vec<&str>>().join(", ")));
+      };
+      assign_status_field(raw_block, status_field, value, game_type_index)
+    }
+    ["party", action] => assign_party(raw_block, Some(action), value, game_type_index),
+    ["djinn", djinni_name] => {
+      let change_description = move_djinni(raw_block, djinni_name, value, game_type_index)?;
+      validate_djinn(raw_block, game_type_index)?;
+      Ok(change_description)
+    }
+    [character_name, "djinn", djinni_name] => {
+      let pc_index = get_pc_index(character_name, game_type_index)?;
+      let change_description = assign_character_djinni(raw_block, pc_index, djinni_name, value, game_type_index)?;
+      validate_djinn(raw_block, game_type_index)?;
+      Ok(change_description)
+    }
+    [character_name, "item", ref key_parts @ ..] => {
+      let pc_index = get_pc_index(character_name, game_type_index)?;
+      assign_character_item(raw_block, pc_index, key_parts, value, game_type_index, game_data)
+    }
+    [character_name, "psynergy", action] => {
+      let pc_index = get_pc_index(character_name, game_type_index)?;
+      assign_character_psynergy(raw_block, pc_index, action, value, game_type_index, game_data)
+    }
+    [character_name, key] => {
+      let pc_index = get_pc_index(character_name, game_type_index)?;
+      let Some(character_field) = find_character_field(key) else {
+        return Err(format!("Unknown field \"{key}\", it should be one of {}!", CHARACTER_FIELDS.iter().map(|character_field| character_field.key).collect::<Vec<&str>>().join(", ")));
+      };
+
+      let new_value = parse_number(value)?;
+      let old_value = read_character_field(raw_block, pc_index, character_field, game_type_index);
+      write_character_field(raw_block, pc_index, character_field, new_value, game_type_index)?;
+
+      Ok(format!("{}'s {}: {old_value} -> {new_value}", PC_LABEL[pc_index], character_field.label))
+    }
+    _ => Err(format!("Unknown target \"{target}\"!")),
+  }
+}
+
+pub fn get_pc_index(character_name: &str, game_type_index: usize) -> Result<usize, String> {
+  find_character(character_name, game_type_index).ok_or_else(|| format!("Unknown character \"{character_name}\", it should be one of {}!", PC_LABEL[..PARTY_MEMBERS_COUNT[game_type_index]].join(", ")))
+}
+
+/// Decimal or hexadecimal with "0x" prefix.
+pub fn parse_number(value: &str) -> Result<u32, String> {
+  let parse_result = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+    Some(hex_value) => u32::from_str_radix(hex_value, 16),
+    None => value.parse::<u32>(),
+  };
+
+  parse_result.map_err(|_| format!("Invalid number \"{value}\"!"))
+}
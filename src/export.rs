@@ -0,0 +1,331 @@
+use std::ops::Range;
+use crate::{BUILD_DATE_LOCATION_INDEX, HEADER_CHECKSUM_LOCATION_INDEX, HEADER_PRIORITY_LOCATION_INDEX, HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX, MAX_LOOP_COUNT, MAX_VALID_SLOT_NUMBER, PARTY_MEMBERS_COUNT, PC_NAME_LOCATION_INDEX, SAVE_SLOT_SIZE};
+use crate::field::{decode_name, describe_build_date, encode_name, from_hex_string, to_hex_string, LEADER_NAME_LOCATION_INDEX, LEADER_NAME_SIZE, PC_DATA_SIZE, PC_LABEL, PC_NAME_SIZE};
+use crate::json::JsonValue;
+use crate::save::{calculate_checksum, get_save_blocks, FLASH_BANK_SIZE};
+
+/// Identify the JSON files created by this tool.
+const EXPORT_FORMAT: &str = "golden_sun_save_converter";
+const EXPORT_VERSION: i64 = 1;
+
+const GAME_TYPE_ID: [&str; 2] = ["TBS", "TLA"];
+const GAME_TYPE_NAME: [&str; 2] = ["Golden Sun", "Golden Sun: The Lost Age"];
+
+/* Export everything this tool knows about a save file.
+   Known fields are exported as readable values, and every other byte is exported as hex in "unknown",
+   so `import_save` can rebuild exactly the same save file from it.
+
+   Names have both "text" and "raw" (hex), "text" is used when importing only if it has been edited.
+   Build date is the same, "build_date" is written to all three copies only if it has been edited. */
+pub fn export_save(raw_save_file: &[u8], game_type_index: usize) -> JsonValue {
+  let block_size = SAVE_SLOT_SIZE[game_type_index];
+  let save_blocks = get_save_blocks(raw_save_file, game_type_index);
+
+  let mut blocks = Vec::new();
+  for i in 0..MAX_LOOP_COUNT[game_type_index] {
+    let raw_block = &raw_save_file[i * block_size..(i + 1) * block_size];
+    let mut members = vec![
+      (String::from("index"), JsonValue::Number(i as i64)),
+      (String::from("offset"), to_hex_number(i * block_size)),
+    ];
+
+    let save_block_option = save_blocks.iter().find(|save_block| save_block.index == i);
+    members.push((String::from("valid"), JsonValue::Bool(save_block_option.is_some())));
+    if let Some(save_block) = save_block_option {
+      members.push((String::from("slot"), JsonValue::Number(i64::from(save_block.slot_number))));
+      members.push((String::from("priority"), JsonValue::Number(i64::from(save_block.priority))));
+      members.push((String::from("checksum"), to_hex_number(usize::from(read_u16(raw_block, HEADER_CHECKSUM_LOCATION_INDEX[0])))));
+      members.push((String::from("calculated_checksum"), to_hex_number(usize::from(calculate_checksum(raw_block, game_type_index)))));
+      members.push((String::from("checksum_valid"), JsonValue::Bool(save_block.is_checksum_valid)));
+
+      // The second half of TBS save data doesn't have these fields.
+      if save_block.slot_number <= MAX_VALID_SLOT_NUMBER {
+        let build_date = read_u16(raw_block, BUILD_DATE_LOCATION_INDEX[game_type_index][0][0]);
+        members.push((String::from("build_date"), to_hex_number(usize::from(build_date))));
+        members.push((String::from("build_date_description"), JsonValue::String(describe_build_date(build_date, game_type_index))));
+        members.push((String::from("build_date_copies"), JsonValue::Array(BUILD_DATE_LOCATION_INDEX[game_type_index].iter()
+          .map(|build_date_location_index| to_hex_number(usize::from(read_u16(raw_block, build_date_location_index[0]))))
+          .collect())));
+        members.push((String::from("leader_name"), export_name(&raw_block[LEADER_NAME_LOCATION_INDEX..LEADER_NAME_LOCATION_INDEX + LEADER_NAME_SIZE])));
+
+        let party = (0..PARTY_MEMBERS_COUNT[game_type_index]).map(|j| {
+          let pc_name_location_index = PC_NAME_LOCATION_INDEX[game_type_index] + j * PC_DATA_SIZE;
+          JsonValue::Object(vec![
+            (String::from("character"), JsonValue::String(String::from(PC_LABEL[j]))),
+            (String::from("name"), export_name(&raw_block[pc_name_location_index..pc_name_location_index + PC_NAME_SIZE])),
+          ])
+        }).collect();
+        members.push((String::from("party"), JsonValue::Array(party)));
+      }
+    }
+
+    let unknown = get_unknown_ranges(raw_block, save_block_option.map(|save_block| save_block.slot_number), game_type_index).into_iter()
+      .map(|unknown_range| JsonValue::Object(vec![
+        (String::from("offset"), to_hex_number(unknown_range.start)),
+        (String::from("hex"), JsonValue::String(to_hex_string(&raw_block[unknown_range]))),
+      ]))
+      .collect();
+    members.push((String::from("unknown"), JsonValue::Array(unknown)));
+
+    blocks.push(JsonValue::Object(members));
+  }
+
+  // Space after the last block, and the second 64KB of 128KB save file.
+  let blocks_end = MAX_LOOP_COUNT[game_type_index] * block_size;
+  JsonValue::Object(vec![
+    (String::from("format"), JsonValue::String(String::from(EXPORT_FORMAT))),
+    (String::from("version"), JsonValue::Number(EXPORT_VERSION)),
+    (String::from("game"), JsonValue::String(String::from(GAME_TYPE_ID[game_type_index]))),
+    (String::from("game_name"), JsonValue::String(String::from(GAME_TYPE_NAME[game_type_index]))),
+    (String::from("layout"), JsonValue::Object(vec![
+      (String::from("file_size"), JsonValue::Number(raw_save_file.len() as i64)),
+      (String::from("block_size"), JsonValue::Number(block_size as i64)),
+      (String::from("block_count"), JsonValue::Number(MAX_LOOP_COUNT[game_type_index] as i64)),
+    ])),
+    (String::from("blocks"), JsonValue::Array(blocks)),
+    (String::from("trailing"), JsonValue::String(to_hex_string(&raw_save_file[blocks_end..]))),
+  ])
+}
+
+/// Rebuild a save file from exported JSON, edited fields are applied and checksums are recalculated.
+pub fn import_save(json: &JsonValue) -> Result<Vec<u8>, String> {
+  if json.get("format").and_then(JsonValue::as_str) != Some(EXPORT_FORMAT) {
+    return Err(String::from("It's not a JSON file exported by this tool!"));
+  }
+  if json.get("version").and_then(JsonValue::as_i64) != Some(EXPORT_VERSION) {
+    return Err(String::from("Unsupported JSON file version!"));
+  }
+  let game_type_id = get_str(json, "game")?;
+  let Some(game_type_index) = GAME_TYPE_ID.iter().position(|id| *id == game_type_id) else {
+    return Err(format!("Unknown game \"{game_type_id}\"!"));
+  };
+
+  let layout = json.get("layout").ok_or("Missing \"layout\"!")?;
+  let file_size = get_usize(layout, "file_size")?;
+  let block_size = SAVE_SLOT_SIZE[game_type_index];
+  let blocks_end = MAX_LOOP_COUNT[game_type_index] * block_size;
+  if file_size < blocks_end || file_size > FLASH_BANK_SIZE * 2 || get_usize(layout, "block_size")? != block_size || get_usize(layout, "block_count")? != MAX_LOOP_COUNT[game_type_index] {
+    return Err(String::from("The layout in JSON file is not valid for this game!"));
+  }
+
+  let mut raw_save_file = vec![0x00; file_size];
+  let trailing = from_hex_string(get_str(json, "trailing")?)?;
+  if trailing.len() != file_size - blocks_end {
+    return Err(String::from("The size of \"trailing\" doesn't match the file size!"));
+  }
+  raw_save_file[blocks_end..].copy_from_slice(&trailing);
+
+  let blocks = json.get("blocks").and_then(JsonValue::as_array).ok_or("Missing \"blocks\"!")?;
+  if blocks.len() != MAX_LOOP_COUNT[game_type_index] {
+    return Err(String::from("The number of blocks doesn't match the layout!"));
+  }
+  for (i, block) in blocks.iter().enumerate() {
+    let raw_block = &mut raw_save_file[i * block_size..(i + 1) * block_size];
+    import_block(raw_block, block, game_type_index).map_err(|error_message| format!("Block {i}: {error_message}"))?;
+  }
+
+  Ok(raw_save_file)
+}
+
+fn import_block(raw_block: &mut [u8], block: &JsonValue, game_type_index: usize) -> Result<(), String> {
+  for unknown in block.get("unknown").and_then(JsonValue::as_array).ok_or("Missing \"unknown\"!")? {
+    let offset = get_usize(unknown, "offset")?;
+    let bytes = from_hex_string(get_str(unknown, "hex")?)?;
+    if offset.checked_add(bytes.len()).is_none_or(|end| end > raw_block.len()) {
+      return Err(format!("Unknown bytes at {offset:#X} are out of range!"));
+    }
+    raw_block[offset..offset + bytes.len()].copy_from_slice(&bytes);
+  }
+
+  if block.get("valid").and_then(JsonValue::as_bool) != Some(true) {
+    return Ok(());
+  }
+
+  let slot_number = u8::try_from(get_usize(block, "slot")?).map_err(|_| String::from("Invalid slot number!"))?;
+  raw_block[HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX] = slot_number;
+  let priority = u16::try_from(get_usize(block, "priority")?).map_err(|_| String::from("Invalid priority!"))?;
+  raw_block[HEADER_PRIORITY_LOCATION_INDEX[0]..=HEADER_PRIORITY_LOCATION_INDEX[1]].copy_from_slice(&priority.to_le_bytes());
+  let checksum = u16::try_from(get_usize(block, "checksum")?).map_err(|_| String::from("Invalid checksum!"))?;
+  raw_block[HEADER_CHECKSUM_LOCATION_INDEX[0]..=HEADER_CHECKSUM_LOCATION_INDEX[1]].copy_from_slice(&checksum.to_le_bytes());
+
+  if slot_number <= MAX_VALID_SLOT_NUMBER {
+    let build_date_copies = block.get("build_date_copies").and_then(JsonValue::as_array).ok_or("Missing \"build_date_copies\"!")?;
+    if build_date_copies.len() != BUILD_DATE_LOCATION_INDEX[game_type_index].len() {
+      return Err(String::from("Invalid \"build_date_copies\"!"));
+    }
+    let mut build_dates = Vec::new();
+    for build_date_copy in build_date_copies {
+      build_dates.push(parse_u16(build_date_copy).ok_or("Invalid \"build_date_copies\"!")?);
+    }
+    // Edited build date is written to all three copies.
+    let build_date = parse_u16(block.get("build_date").ok_or("Missing \"build_date\"!")?).ok_or("Invalid \"build_date\"!")?;
+    if build_date != build_dates[0] {
+      build_dates.fill(build_date);
+    }
+    for (build_date_location_index, build_date) in BUILD_DATE_LOCATION_INDEX[game_type_index].iter().zip(build_dates) {
+      raw_block[build_date_location_index[0]..=build_date_location_index[1]].copy_from_slice(&build_date.to_le_bytes());
+    }
+
+    let leader_name = import_name(block.get("leader_name").ok_or("Missing \"leader_name\"!")?, LEADER_NAME_SIZE)?;
+    raw_block[LEADER_NAME_LOCATION_INDEX..LEADER_NAME_LOCATION_INDEX + LEADER_NAME_SIZE].copy_from_slice(&leader_name);
+
+    let party = block.get("party").and_then(JsonValue::as_array).ok_or("Missing \"party\"!")?;
+    if party.len() != PARTY_MEMBERS_COUNT[game_type_index] {
+      return Err(String::from("Invalid \"party\"!"));
+    }
+    for (j, party_member) in party.iter().enumerate() {
+      let pc_name = import_name(party_member.get("name").ok_or("Missing party member's \"name\"!")?, PC_NAME_SIZE)?;
+      let pc_name_location_index = PC_NAME_LOCATION_INDEX[game_type_index] + j * PC_DATA_SIZE;
+      raw_block[pc_name_location_index..pc_name_location_index + PC_NAME_SIZE].copy_from_slice(&pc_name);
+    }
+  }
+
+  // Keep invalid checksums as they are, so an unedited save file is rebuilt byte by byte.
+  if block.get("checksum_valid").and_then(JsonValue::as_bool) == Some(true) {
+    let checksum = calculate_checksum(raw_block, game_type_index);
+    raw_block[HEADER_CHECKSUM_LOCATION_INDEX[0]..=HEADER_CHECKSUM_LOCATION_INDEX[1]].copy_from_slice(&checksum.to_le_bytes());
+  }
+
+  Ok(())
+}
+
+fn export_name(bytes: &[u8]) -> JsonValue {
+  JsonValue::Object(vec![
+    (String::from("text"), JsonValue::String(decode_name(bytes))),
+    (String::from("raw"), JsonValue::String(to_hex_string(bytes))),
+  ])
+}
+
+/// Use "text" if it has been edited, otherwise use "raw".
+fn import_name(name: &JsonValue, size: usize) -> Result<Vec<u8>, String> {
+  let raw = from_hex_string(get_str(name, "raw")?)?;
+  if raw.len() != size {
+    return Err(format!("The size of name \"{}\" is not valid!", get_str(name, "raw")?));
+  }
+  let text = get_str(name, "text")?;
+  if text == decode_name(&raw) {
+    return Ok(raw);
+  }
+
+  encode_name(text, size).ok_or_else(|| format!("Name \"{text}\" is too long or contains characters that can't be stored in save file!"))
+}
+
+/// Bytes of a block that are not exported as known fields.
+fn get_unknown_ranges(raw_block: &[u8], slot_number_option: Option<u8>, game_type_index: usize) -> Vec<Range<usize>> {
+  let mut is_known_byte = vec![false; raw_block.len()];
+  if let Some(slot_number) = slot_number_option {
+    is_known_byte[HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX] = true;
+    is_known_byte[HEADER_CHECKSUM_LOCATION_INDEX[0]..=HEADER_CHECKSUM_LOCATION_INDEX[1]].fill(true);
+    is_known_byte[HEADER_PRIORITY_LOCATION_INDEX[0]..=HEADER_PRIORITY_LOCATION_INDEX[1]].fill(true);
+    if slot_number <= MAX_VALID_SLOT_NUMBER {
+      for build_date_location_index in BUILD_DATE_LOCATION_INDEX[game_type_index] {
+        is_known_byte[build_date_location_index[0]..=build_date_location_index[1]].fill(true);
+      }
+      is_known_byte[LEADER_NAME_LOCATION_INDEX..LEADER_NAME_LOCATION_INDEX + LEADER_NAME_SIZE].fill(true);
+      for j in 0..PARTY_MEMBERS_COUNT[game_type_index] {
+        let pc_name_location_index = PC_NAME_LOCATION_INDEX[game_type_index] + j * PC_DATA_SIZE;
+        is_known_byte[pc_name_location_index..pc_name_location_index + PC_NAME_SIZE].fill(true);
+      }
+    }
+  }
+
+  let mut unknown_ranges: Vec<Range<usize>> = Vec::new();
+  for (i, is_known) in is_known_byte.into_iter().enumerate() {
+    if is_known {
+      continue;
+    }
+    match unknown_ranges.last_mut() {
+      Some(last_range) if last_range.end == i => last_range.end = i + 1,
+      _ => unknown_ranges.push(i..i + 1),
+    }
+  }
+
+  unknown_ranges
+}
+
+fn read_u16(raw_block: &[u8], location_index: usize) -> u16 {
+  u16::from_le_bytes([raw_block[location_index], raw_block[location_index + 1]])
+}
+
+/// Offsets, checksums and build dates are exported as hex strings like "0x1C85".
+fn to_hex_number(value: usize) -> JsonValue {
+  JsonValue::String(format!("{value:#06X}"))
+}
+
+/// Accept both hex strings and plain numbers, in case a user edits them by hand.
+fn parse_usize(value: &JsonValue) -> Option<usize> {
+  match value {
+    JsonValue::Number(number) => usize::try_from(*number).ok(),
+    JsonValue::String(hex_string) => {
+      let digits = hex_string.strip_prefix("0x").or_else(|| hex_string.strip_prefix("0X"))?;
+      usize::from_str_radix(digits, 16).ok()
+    }
+    _ => None,
+  }
+}
+
+fn parse_u16(value: &JsonValue) -> Option<u16> {
+  parse_usize(value).and_then(|value| u16::try_from(value).ok())
+}
+
+fn get_usize(json: &JsonValue, key: &str) -> Result<usize, String> {
+  json.get(key).and_then(parse_usize).ok_or_else(|| format!("Missing or invalid \"{key}\"!"))
+}
+
+fn get_str<'a>(json: &'a JsonValue, key: &str) -> Result<&'a str, String> {
+  json.get(key).and_then(JsonValue::as_str).ok_or_else(|| format!("Missing or invalid \"{key}\"!"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::json::parse_json;
+
+  #[test]
+  fn unknown_bytes_out_of_range_are_refused() {
+    let mut raw_block = vec![0u8; SAVE_SLOT_SIZE[0]];
+    for offset in ["0xFFFFFFFFFFFFFFFF", "0xFFF", "0x1000"] {
+      let block = parse_json(&format!(r#"{{"unknown": [{{"offset": "{offset}", "hex": "0102"}}], "valid": false}}"#)).unwrap();
+      assert!(import_block(&mut raw_block, &block, 0).is_err(), "{offset}");
+    }
+    let block = parse_json(r#"{"unknown": [{"offset": "0xFFE", "hex": "0102"}], "valid": false}"#).unwrap();
+    import_block(&mut raw_block, &block, 0).unwrap();
+    assert_eq!(raw_block[0xFFE..], [0x01, 0x02]);
+  }
+
+  #[test]
+  fn numbers_out_of_range_are_refused() {
+    let parse_text = |text: &str| parse_u16(&parse_json(text).unwrap());
+    assert_eq!(parse_text("65535"), Some(0xFFFF));
+    assert_eq!(parse_text(r#""0x1C85""#), Some(0x1C85));
+    for text in ["-1", "65536", "-9223372036854775808", r#""0x10000""#, r#""0x""#, r#""1C85""#, r#""0x-1""#, r#""0x１""#, "true", "null", "[1]"] {
+      assert_eq!(parse_text(text), None, "{text}");
+    }
+  }
+
+  #[test]
+  fn names_are_checked() {
+    let name = |text: &str, raw: &str| parse_json(&format!(r#"{{"text": "{text}", "raw": "{raw}"}}"#)).unwrap();
+    assert_eq!(import_name(&name("Isaac", "4973616163000000"), 8), Ok(b"Isaac\0\0\0".to_vec()));
+    assert!(import_name(&name("Isaac", "49736161"), 8).is_err());
+    assert!(import_name(&name("Isaac", "497361616300000G"), 8).is_err());
+    assert!(import_name(&name("TooLongName", "4973616163000000"), 8).is_err());
+    assert!(import_name(&name("\u{1F600}", "4973616163000000"), 8).is_err());
+  }
+
+  #[test]
+  fn oversized_file_is_refused_before_allocating() {
+    let json = parse_json(r#"{"version": 1, "game": "TBS", "layout": {"file_size": "0x7FFFFFFFFFFFFFFF", "block_size": "0x1000", "block_count": 16}}"#).unwrap();
+    assert!(import_save(&json).is_err());
+  }
+
+  #[test]
+  fn export_and_import_round_trip() {
+    let mut raw_save_file = vec![0xFFu8; FLASH_BANK_SIZE];
+    raw_save_file[0x123] = 0x45;
+    raw_save_file[0x5000..0x5007].copy_from_slice(b"CAMELOT");
+    raw_save_file[0x5007] = 0x0E;
+    let json = parse_json(&export_save(&raw_save_file, 0).to_pretty_string()).unwrap();
+    assert_eq!(import_save(&json).unwrap(), raw_save_file);
+  }
+}
@@ -0,0 +1,183 @@
+use std::ops::Range;
+use crate::character::get_character_data_label;
+use crate::flags::{EVENT_FLAGS_LOCATION_INDEX, EVENT_FLAG_COUNT};
+use crate::location::LOCATION_LOCATION_INDEX;
+use crate::party::{PARTY_MEMBER_FLAGS_LOCATION_INDEX, PARTY_ORDER_LOCATION_INDEX};
+use crate::status::STATUS_FIELDS;
+use crate::{GS_BUILD_DATE, BUILD_DATE_LOCATION_INDEX, HEADER_CHECKSUM_LOCATION_INDEX, HEADER_PRIORITY_LOCATION_INDEX, HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX, PARTY_MEMBERS_COUNT, PC_NAME_LOCATION_INDEX};
+
+/// Main characters' English names, only used as labels in output.
+pub const PC_LABEL: [&str; 8] = ["Isaac", "Garet", "Ivan", "Mia", "Felix", "Jenna", "Sheba", "Piers"];
+
+/// The size of each playable character's data.
+pub const PC_DATA_SIZE: usize = 0x14C;
+
+/// The size of each playable character's name in character data.
+pub const PC_NAME_SIZE: usize = 15;
+
+/// The location and size of the party leader name shown in save select menu.
+pub const LEADER_NAME_LOCATION_INDEX: usize = 0x10;
+pub const LEADER_NAME_SIZE: usize = 12;
+
+/// Other known parts of save data ([TBS offset, TLA offset], size, label), only used as labels in output.
+const SAVE_DATA_RANGE_LABEL: [([usize; 2], usize, &str); 4] = [
+  ([PARTY_MEMBER_FLAGS_LOCATION_INDEX; 2], 1, "Party members"),
+  ([EVENT_FLAGS_LOCATION_INDEX; 2], EVENT_FLAG_COUNT / 8, "Event flags"),
+  (LOCATION_LOCATION_INDEX, 8, "Location"),
+  (PARTY_ORDER_LOCATION_INDEX, 8, "Party order"),
+];
+
+/// Build date versions in the same order as `GS_BUILD_DATE`, only used as labels in output.
+pub const BUILD_DATE_LABEL: [&str; 6] = ["Japan", "USA/Europe", "Germany", "Spain", "France", "Italy"];
+
+#[derive(Clone, Copy)]
+pub enum FieldKind {
+  // Unsigned little-endian number
+  Number,
+  Checksum,
+  BuildDate,
+  Name,
+}
+
+/// A known field in a save data block.
+/// The offset is relative to the start of the block (including the 0x10 header).
+pub struct SaveField {
+  pub name: String,
+  pub kind: FieldKind,
+  pub offset: usize,
+  pub size: usize,
+}
+
+/// Get all fields this tool knows about in a save data block.
+pub fn get_save_fields(game_type_index: usize) -> Vec<SaveField> {
+  let mut save_fields = vec![
+    SaveField { name: String::from("Slot number"), kind: FieldKind::Number, offset: HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX, size: 1 },
+    SaveField { name: String::from("Checksum"), kind: FieldKind::Checksum, offset: HEADER_CHECKSUM_LOCATION_INDEX[0], size: 2 },
+    SaveField { name: String::from("Priority"), kind: FieldKind::Number, offset: HEADER_PRIORITY_LOCATION_INDEX[0], size: 2 },
+    SaveField { name: String::from("Leader name"), kind: FieldKind::Name, offset: LEADER_NAME_LOCATION_INDEX, size: LEADER_NAME_SIZE },
+  ];
+
+  for (i, build_date_location_index) in BUILD_DATE_LOCATION_INDEX[game_type_index].iter().enumerate() {
+    save_fields.push(SaveField { name: format!("Build date #{}", i + 1), kind: FieldKind::BuildDate, offset: build_date_location_index[0], size: 2 });
+  }
+
+  for (i, pc_label) in PC_LABEL.iter().enumerate().take(PARTY_MEMBERS_COUNT[game_type_index]) {
+    save_fields.push(SaveField { name: format!("{pc_label}'s name"), kind: FieldKind::Name, offset: PC_NAME_LOCATION_INDEX[game_type_index] + i * PC_DATA_SIZE, size: PC_NAME_SIZE });
+  }
+
+  save_fields
+}
+
+/// Get all ranges of bytes that differ between two buffers of the same size, adjacent bytes are merged.
+pub fn get_changed_ranges(old_bytes: &[u8], new_bytes: &[u8]) -> Vec<Range<usize>> {
+  let mut changed_ranges: Vec<Range<usize>> = Vec::new();
+  for (i, (old_byte, new_byte)) in old_bytes.iter().zip(new_bytes.iter()).enumerate() {
+    if old_byte == new_byte {
+      continue;
+    }
+    match changed_ranges.last_mut() {
+      Some(last_range) if last_range.end == i => last_range.end = i + 1,
+      _ => changed_ranges.push(i..i + 1),
+    }
+  }
+
+  changed_ranges
+}
+
+/// Get a readable string of a field's value.
+pub fn describe_field_value(save_field: &SaveField, bytes: &[u8], game_type_index: usize) -> String {
+  match save_field.kind {
+    FieldKind::Number => bytes.iter().rev().fold(0u64, |value, byte| (value << 8) | u64::from(*byte)).to_string(),
+    FieldKind::Checksum => format!("{:#06X}", u16::from_le_bytes([bytes[0], bytes[1]])),
+    FieldKind::BuildDate => describe_build_date(u16::from_le_bytes([bytes[0], bytes[1]]), game_type_index),
+    FieldKind::Name => format!("\"{}\"", decode_name(bytes)),
+  }
+}
+
+/* Build date value: 0x1000 | 1024 * year + 64 * month + day
+   Output example: "0x159C (2001/06/28, Japan)" */
+pub fn describe_build_date(build_date: u16, game_type_index: usize) -> String {
+  let value = usize::from(build_date) - 0x1000.min(usize::from(build_date));
+  let version = match GS_BUILD_DATE[game_type_index].iter().position(|date| *date == build_date) {
+    Some(build_date_type_index) => BUILD_DATE_LABEL[build_date_type_index],
+    None => "unknown version",
+  };
+
+  format!("{:#06X} ({}/{:02}/{:02}, {})", build_date, 2000 + (value >> 10), (value >> 6) & 0x0F, value & 0x3F, version)
+}
+
+/* Decode a name stored in save file.
+   Western versions use ASCII with some Latin-1 accented letters,
+   Japanese version uses JIS X 0201 half-width katakana (0xA1 - 0xDF).
+   The Chinese fan translations use 2 bytes glyph indices, they are shown as hex. */
+pub fn decode_name(bytes: &[u8]) -> String {
+  let name_length = bytes.iter().rposition(|byte| *byte != 0x00).map_or(0, |i| i + 1);
+  let name_bytes = &bytes[..name_length];
+  if name_bytes.iter().any(|byte| *byte < 0x20 || (0x7F..0xA1).contains(byte)) {
+    return format!("[{}]", name_bytes.iter().map(|byte| format!("{byte:02X}")).collect::<Vec<String>>().join(" "));
+  }
+
+  let is_katakana = name_bytes.iter().any(|byte| (0xA1..=0xDF).contains(byte)) && name_bytes.iter().all(|byte| *byte < 0xE0);
+  name_bytes.iter().map(|byte| {
+    if is_katakana && (0xA1..=0xDF).contains(byte) {
+      char::from_u32(0xFF61 + u32::from(*byte) - 0xA1).unwrap()
+    } else {
+      char::from(*byte)
+    }
+  }).collect()
+}
+
+/// Describe which part of save data a block offset belongs to, like "Isaac's data +0x0F".
+pub fn get_offset_label(offset: usize, game_type_index: usize) -> String {
+  if offset < 0x10 {
+    return String::from("header");
+  }
+  let pc_data_start = PC_NAME_LOCATION_INDEX[game_type_index];
+  let pc_data_end = pc_data_start + PARTY_MEMBERS_COUNT[game_type_index] * PC_DATA_SIZE;
+  if (pc_data_start..pc_data_end).contains(&offset) {
+    let pc_index = (offset - pc_data_start) / PC_DATA_SIZE;
+    let pc_data_offset = (offset - pc_data_start) % PC_DATA_SIZE;
+    return match get_character_data_label(pc_data_offset) {
+      Some(character_data_label) => format!("{}'s data +{pc_data_offset:#04X}, {character_data_label}", PC_LABEL[pc_index]),
+      None => format!("{}'s data +{pc_data_offset:#04X}", PC_LABEL[pc_index]),
+    };
+  }
+
+  if let Some(status_field) = STATUS_FIELDS.iter().find(|status_field| (status_field.offset[game_type_index]..status_field.offset[game_type_index] + status_field.size).contains(&offset)) {
+    return String::from(status_field.label);
+  }
+  SAVE_DATA_RANGE_LABEL.iter()
+    .find(|(offsets, size, _)| (offsets[game_type_index]..offsets[game_type_index] + size).contains(&offset))
+    .map_or_else(|| String::from("save data"), |(_, _, label)| (*label).to_string())
+}
+
+pub fn to_hex_string(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+pub fn from_hex_string(hex_string: &str) -> Result<Vec<u8>, String> {
+  if !hex_string.len().is_multiple_of(2) || !hex_string.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+    return Err(format!("Invalid hex string \"{hex_string}\"!"));
+  }
+
+  Ok((0..hex_string.len()).step_by(2).map(|i| u8::from_str_radix(&hex_string[i..i + 2], 16).unwrap()).collect())
+}
+
+/// The reverse of `decode_name`, returns `None` if a character can't be stored in save file.
+pub fn encode_name(name: &str, size: usize) -> Option<Vec<u8>> {
+  let mut bytes = if let Some(hex_string) = name.strip_prefix('[').and_then(|name| name.strip_suffix(']')) {
+    from_hex_string(&hex_string.replace(' ', "")).ok()?
+  } else {
+    name.chars().map(|c| match u32::from(c) {
+      code_point @ (0x20..=0x7E | 0xA1..=0xFF) => Some(code_point as u8),
+      code_point @ 0xFF61..=0xFF9F => Some((code_point - 0xFF61 + 0xA1) as u8),
+      _ => None,
+    }).collect::<Option<Vec<u8>>>()?
+  };
+  if bytes.len() > size {
+    return None;
+  }
+  bytes.resize(size, 0x00);
+
+  Some(bytes)
+}
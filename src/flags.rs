@@ -0,0 +1,131 @@
+use std::fmt::Write;
+use crate::gamedata::{GameData, FLAG_DATA_FILE_NAME};
+
+/* Event flags, one bit for each flag, from the Golden Sun Hacking Community RAM map (block offset = RAM offset + 0x10).
+   0x50   0x1000 flags (0x200 bytes), flag 0x000 is bit 0 of the first byte, same in TBS and TLA
+
+   The game uses them for everything that happened in the story, like bosses beaten, djinn collected and chests opened.
+   Flags 0x000 - 0x007 are party members (see party.rs).
+   Only the party member flags are named in the built-in flag data, there is no verified list of the other flags to ship,
+   their names come from the flag data files in data directory. */
+pub const EVENT_FLAGS_LOCATION_INDEX: usize = 0x50;
+pub const EVENT_FLAG_COUNT: usize = 0x1000;
+const PARTY_MEMBER_FLAG_COUNT: u16 = 8;
+
+pub fn read_flag(raw_block: &[u8], flag_index: u16) -> bool {
+  raw_block[EVENT_FLAGS_LOCATION_INDEX + usize::from(flag_index) / 8] & (1 << (flag_index % 8)) != 0
+}
+
+fn write_flag(raw_block: &mut [u8], flag_index: u16, is_set: bool) {
+  let location_index = EVENT_FLAGS_LOCATION_INDEX + usize::from(flag_index) / 8;
+  if is_set {
+    raw_block[location_index] |= 1 << (flag_index % 8);
+  } else {
+    raw_block[location_index] &= !(1 << (flag_index % 8));
+  }
+}
+
+fn get_set_flags(raw_block: &[u8]) -> Vec<u16> {
+  (0..EVENT_FLAG_COUNT as u16).filter(|flag_index| read_flag(raw_block, *flag_index)).collect()
+}
+
+/// A flag's index and name, like "0x004  Party: Felix", unnamed flags only have the index.
+fn describe_flag(flag_index: u16, game_type_index: usize, game_data: &GameData) -> String {
+  match game_data.get_flag_name(flag_index, game_type_index) {
+    Some(flag_name) => format!("{flag_index:#05X}  {flag_name}"),
+    None => format!("{flag_index:#05X}"),
+  }
+}
+
+/// A note about the listed flags without a name, as most flags are not named in the built-in data.
+fn get_unnamed_flag_note(flag_indexes: &[u16], game_type_index: usize, game_data: &GameData) -> Option<String> {
+  let unnamed_flag_count = flag_indexes.iter().filter(|flag_index| game_data.get_flag_name(**flag_index, game_type_index).is_none()).count();
+  (unnamed_flag_count > 0).then(|| format!("Note: {unnamed_flag_count} flag(s) have no name, only the party member flags are named in this tool, add names to {} in data directory (see \"--data\").", FLAG_DATA_FILE_NAME[game_type_index]))
+}
+
+/// List the flags that are set, with "all", named flags that are not set are listed too.
+pub fn get_flags_list_report(raw_block: &[u8], game_type_index: usize, game_data: &GameData, is_all: bool) -> String {
+  let mut report = String::new();
+  let set_flags = get_set_flags(raw_block);
+  writeln!(report, "{} of {EVENT_FLAG_COUNT} flags are set.", set_flags.len()).unwrap();
+
+  let mut flag_indexes = set_flags.clone();
+  if is_all {
+    flag_indexes.extend(game_data.flags[game_type_index].iter().map(|flag_data| flag_data.index).filter(|flag_index| usize::from(*flag_index) < EVENT_FLAG_COUNT));
+    flag_indexes.sort_unstable();
+    flag_indexes.dedup();
+  }
+  for flag_index in &flag_indexes {
+    let state = if set_flags.contains(flag_index) { "[x]" } else { "[ ]" };
+    writeln!(report, "  {state} {}", describe_flag(*flag_index, game_type_index, game_data)).unwrap();
+  }
+  if let Some(note) = get_unnamed_flag_note(&flag_indexes, game_type_index, game_data) {
+    writeln!(report, "{note}").unwrap();
+  }
+
+  report
+}
+
+/// Flags that are set ("+") or cleared ("-") in block B, compared to block A.
+pub fn get_flags_diff_report(raw_block_a: &[u8], raw_block_b: &[u8], game_type_index: usize, game_data: &GameData) -> String {
+  let mut report = String::new();
+  let mut changed_flags = Vec::new();
+  for flag_index in 0..EVENT_FLAG_COUNT as u16 {
+    match (read_flag(raw_block_a, flag_index), read_flag(raw_block_b, flag_index)) {
+      (false, true) => writeln!(report, "+ {}", describe_flag(flag_index, game_type_index, game_data)).unwrap(),
+      (true, false) => writeln!(report, "- {}", describe_flag(flag_index, game_type_index, game_data)).unwrap(),
+      _ => continue,
+    }
+    changed_flags.push(flag_index);
+  }
+  if report.is_empty() {
+    writeln!(report, "The event flags are identical.").unwrap();
+  }
+  if let Some(note) = get_unnamed_flag_note(&changed_flags, game_type_index, game_data) {
+    writeln!(report, "{note}").unwrap();
+  }
+
+  report
+}
+
+/// Set or clear flags named by their indexes or names, returns a line for each flag.
+pub fn set_flags(raw_block: &mut [u8], flags: &[&String], is_set: bool, game_type_index: usize, game_data: &GameData) -> Result<Vec<String>, String> {
+  let mut change_descriptions = Vec::new();
+  for flag in flags {
+    let flag_index = game_data.find_flag_index(flag, game_type_index)?;
+    if usize::from(flag_index) >= EVENT_FLAG_COUNT {
+      return Err(format!("Flag index can't be more than {:#05X}!", EVENT_FLAG_COUNT - 1));
+    }
+    let old_state = if read_flag(raw_block, flag_index) { "set" } else { "clear" };
+    write_flag(raw_block, flag_index, is_set);
+    let mut change_description = format!("{} ({old_state} -> {})", describe_flag(flag_index, game_type_index, game_data), if is_set { "set" } else { "clear" });
+    if flag_index < PARTY_MEMBER_FLAG_COUNT {
+      change_description.push_str("\n  Warning: This flag is a party member, the party order is not changed, use \"edit --set party=...\" instead.");
+    }
+    change_descriptions.push(change_description);
+  }
+
+  Ok(change_descriptions)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SAVE_SLOT_SIZE;
+
+  #[test]
+  fn unnamed_flags_are_noted() {
+    let game_data = GameData::load(None).unwrap();
+    let mut raw_block = vec![0u8; SAVE_SLOT_SIZE[1]];
+    write_flag(&mut raw_block, 0x004, true);
+    assert_eq!(get_flags_list_report(&raw_block, 1, &game_data, false), "1 of 4096 flags are set.\n  [x] 0x004  Party: Felix\n");
+
+    let mut other_raw_block = raw_block.clone();
+    write_flag(&mut other_raw_block, 0x123, true);
+    write_flag(&mut other_raw_block, 0x004, false);
+    assert_eq!(
+      get_flags_diff_report(&raw_block, &other_raw_block, 1, &game_data),
+      "- 0x004  Party: Felix\n+ 0x123\nNote: 1 flag(s) have no name, only the party member flags are named in this tool, add names to tla_flags.txt in data directory (see \"--data\").\n"
+    );
+  }
+}
@@ -0,0 +1,394 @@
+use std::fs;
+use std::path::Path;
+use crate::character::ELEMENT_LABEL;
+use crate::edit::parse_number;
+use crate::field::PC_LABEL;
+
+/* Game data that this tool doesn't know by itself, like item names, is read from text files in a data directory ("--data").
+   Every file has one entry on each line, values are separated by commas, "#" starts a comment.
+
+   items.txt: <id>, <name>, <type>, [flags], [psynergy], [stat bonuses]
+   Type is one of "weapon", "armor", "shield", "helm", "boots", "ring", "undershirt" and "other",
+   flags are "stackable", "cursed" and "class" (changes the class when equipped, like Mysterious Card), separated by spaces,
+   psynergy is the psynergy the item gives, like "Lash" for Lash Pebble.
+   Stats are always written like "10/0/5/0/0/0" (HP/PP/attack/defense/agility/luck), stat bonuses of an item are only added when it's equipped.
+
+   psynergy.txt: <id>, <name>
+
+   classes.txt: <id>, <name>, [psynergy learned by level], [required elemental levels], [base elements], [stat multipliers]
+   Psynergy learned by level is like "Quake@1 Earthquake@8", psynergy can be named by its ID or by its name in psynergy.txt.
+   Required elemental levels are like "5/0/0/0" (Venus/Mercury/Mars/Jupiter), base elements are the elements of characters who can have the class,
+   like "venus mars". Classes without them are not used to compute classes. Stat multipliers are percentages, like "120/100/110/100/100/100".
+
+   djinn.txt: <djinni>, <stat bonuses>
+   Stat bonuses of a set djinni, djinn are named by their English names.
+
+   growth.txt: <character>, <level>, <base stats>
+   Base stats of a character at a level, characters are named by their English names.
+
+   tbs_flags.txt, tla_flags.txt: <index>, <name>
+   Event flag names, the files in "data" directory of this repository are built in, names in data directory are added to them.
+
+   tbs_maps.txt, tla_maps.txt: <map id>, <name>
+
+   tbs_locations.txt, tla_locations.txt: <name>, <map id>, <entrance id>, <x>, <y>
+   Named places a save can resume at, like a sanctum or a town entrance.
+
+   Without a data file, entries are shown by their IDs. */
+pub const ITEM_DATA_FILE_NAME: &str = "items.txt";
+const PSYNERGY_DATA_FILE_NAME: &str = "psynergy.txt";
+pub const CLASS_DATA_FILE_NAME: &str = "classes.txt";
+pub const DJINN_DATA_FILE_NAME: &str = "djinn.txt";
+pub const GROWTH_DATA_FILE_NAME: &str = "growth.txt";
+pub const FLAG_DATA_FILE_NAME: [&str; 2] = ["tbs_flags.txt", "tla_flags.txt"];
+const MAP_DATA_FILE_NAME: [&str; 2] = ["tbs_maps.txt", "tla_maps.txt"];
+const LOCATION_DATA_FILE_NAME: [&str; 2] = ["tbs_locations.txt", "tla_locations.txt"];
+const BUILT_IN_FLAG_DATA: [&str; 2] = [include_str!("../data/tbs_flags.txt"), include_str!("../data/tla_flags.txt")];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ItemType {
+  Weapon,
+  Armor,
+  Shield,
+  Helm,
+  Boots,
+  Ring,
+  Undershirt,
+  Other,
+}
+
+impl ItemType {
+  fn from_label(label: &str) -> Option<Self> {
+    Some(match label.to_ascii_lowercase().as_str() {
+      "weapon" => Self::Weapon,
+      "armor" => Self::Armor,
+      "shield" => Self::Shield,
+      "helm" => Self::Helm,
+      "boots" => Self::Boots,
+      "ring" => Self::Ring,
+      "undershirt" => Self::Undershirt,
+      "other" => Self::Other,
+      _ => return None,
+    })
+  }
+
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::Weapon => "weapon",
+      Self::Armor => "armor",
+      Self::Shield => "shield",
+      Self::Helm => "helm",
+      Self::Boots => "boots",
+      Self::Ring => "ring",
+      Self::Undershirt => "undershirt",
+      Self::Other => "other",
+    }
+  }
+}
+
+pub struct ItemData {
+  pub id: u16,
+  pub name: String,
+  pub item_type: ItemType,
+  pub is_stackable: bool,
+  pub is_cursed: bool,
+  pub is_class_item: bool,
+  pub psynergy_id_option: Option<u16>,
+  pub stat_bonuses_option: Option<[i32; 6]>,
+}
+
+pub struct PsynergyData {
+  pub id: u16,
+  pub name: String,
+}
+
+pub struct ClassData {
+  pub id: u8,
+  pub name: String,
+  // (Psynergy ID, level), the psynergy is learned at that level.
+  pub psynergy_levels: Vec<(u16, u8)>,
+  // Venus, Mercury, Mars, Jupiter
+  pub required_levels_option: Option<[u8; 4]>,
+  pub base_element_indexes: Vec<usize>,
+  pub stat_multipliers_option: Option<[i32; 6]>,
+}
+
+pub struct DjinniData {
+  pub name: String,
+  pub stat_bonuses: [i32; 6],
+}
+
+pub struct GrowthData {
+  pub pc_index: usize,
+  pub level: u8,
+  pub base_stats: [i32; 6],
+}
+
+pub struct FlagData {
+  pub index: u16,
+  pub name: String,
+}
+
+pub struct MapData {
+  pub id: u16,
+  pub name: String,
+}
+
+pub struct LocationData {
+  pub name: String,
+  pub map_id: u16,
+  pub entrance_id: u16,
+  pub x: u16,
+  pub y: u16,
+}
+
+#[derive(Default)]
+pub struct GameData {
+  pub items: Vec<ItemData>,
+  pub psynergy: Vec<PsynergyData>,
+  pub classes: Vec<ClassData>,
+  pub djinn: Vec<DjinniData>,
+  pub growth: Vec<GrowthData>,
+  // TBS, TLA
+  pub flags: [Vec<FlagData>; 2],
+  pub maps: [Vec<MapData>; 2],
+  pub locations: [Vec<LocationData>; 2],
+}
+
+impl GameData {
+  /// Load all data files in the data directory, missing files are fine.
+  pub fn load(data_dir_option: Option<&Path>) -> Result<Self, String> {
+    let mut game_data = Self::default();
+    for (game_type_index, flag_data_file_name) in FLAG_DATA_FILE_NAME.iter().enumerate() {
+      game_data.add_flags(game_type_index, flag_data_file_name, parse_data_text(BUILT_IN_FLAG_DATA[game_type_index]))?;
+    }
+    let Some(data_dir) = data_dir_option else {
+      return Ok(game_data);
+    };
+    for (game_type_index, flag_data_file_name) in FLAG_DATA_FILE_NAME.iter().enumerate() {
+      game_data.add_flags(game_type_index, flag_data_file_name, read_data_file(&data_dir.join(flag_data_file_name))?)?;
+    }
+
+    for (game_type_index, map_data_file_name) in MAP_DATA_FILE_NAME.iter().enumerate() {
+      for (line_number, values) in read_data_file(&data_dir.join(map_data_file_name))? {
+        let error_message = || format!("{map_data_file_name} line {line_number}: it should be \"<map id>, <name>\"!");
+        let (Some(id), Some(name)) = (values.first(), values.get(1)) else {
+          return Err(error_message());
+        };
+        game_data.maps[game_type_index].push(MapData { id: u16::try_from(parse_number(id)?).map_err(|_| error_message())?, name: name.clone() });
+      }
+    }
+
+    for (game_type_index, location_data_file_name) in LOCATION_DATA_FILE_NAME.iter().enumerate() {
+      for (line_number, values) in read_data_file(&data_dir.join(location_data_file_name))? {
+        let error_message = || format!("{location_data_file_name} line {line_number}: it should be \"<name>, <map id>, <entrance id>, <x>, <y>\"!");
+        let [name, map_id, entrance_id, x, y] = &values[..] else {
+          return Err(error_message());
+        };
+        let parse_u16 = |value: &str| parse_number(value).ok().and_then(|number| u16::try_from(number).ok()).ok_or_else(error_message);
+        game_data.locations[game_type_index].push(LocationData { name: name.clone(), map_id: parse_u16(map_id)?, entrance_id: parse_u16(entrance_id)?, x: parse_u16(x)?, y: parse_u16(y)? });
+      }
+    }
+
+    // Psynergy is loaded first, it's named in other data files.
+    for (line_number, values) in read_data_file(&data_dir.join(PSYNERGY_DATA_FILE_NAME))? {
+      let error_message = || format!("{PSYNERGY_DATA_FILE_NAME} line {line_number}: it should be \"<id>, <name>\"!");
+      let (Some(id), Some(name)) = (values.first(), values.get(1)) else {
+        return Err(error_message());
+      };
+      game_data.psynergy.push(PsynergyData { id: u16::try_from(parse_number(id)?).map_err(|_| error_message())?, name: name.clone() });
+    }
+
+    for (line_number, values) in read_data_file(&data_dir.join(ITEM_DATA_FILE_NAME))? {
+      let error_message = || format!("{ITEM_DATA_FILE_NAME} line {line_number}: it should be \"<id>, <name>, <type>, [flags], [psynergy], [hp/pp/attack/defense/agility/luck]\"!");
+      let (Some(id), Some(name), Some(item_type)) = (values.first(), values.get(1), values.get(2).and_then(|label| ItemType::from_label(label))) else {
+        return Err(error_message());
+      };
+      let flags = values.get(3).map_or("", String::as_str);
+      game_data.items.push(ItemData {
+        id: u16::try_from(parse_number(id)?).map_err(|_| error_message())?,
+        name: name.clone(),
+        item_type,
+        is_stackable: flags.split_whitespace().any(|flag| flag.eq_ignore_ascii_case("stackable")),
+        is_cursed: flags.split_whitespace().any(|flag| flag.eq_ignore_ascii_case("cursed")),
+        is_class_item: flags.split_whitespace().any(|flag| flag.eq_ignore_ascii_case("class")),
+        psynergy_id_option: match values.get(4).filter(|psynergy| !psynergy.is_empty()) {
+          Some(psynergy) => Some(game_data.find_psynergy_id(psynergy).map_err(|error_message| format!("{ITEM_DATA_FILE_NAME} line {line_number}: {error_message}"))?),
+          None => None,
+        },
+        stat_bonuses_option: match values.get(5) {
+          Some(stat_bonuses) => Some(parse_stats(stat_bonuses).ok_or_else(error_message)?),
+          None => None,
+        },
+      });
+    }
+
+    for (line_number, values) in read_data_file(&data_dir.join(CLASS_DATA_FILE_NAME))? {
+      let error_message = || format!("{CLASS_DATA_FILE_NAME} line {line_number}: it should be \"<id>, <name>, [psynergy@level ...], [venus/mercury/mars/jupiter levels], [base elements], [hp/pp/attack/defense/agility/luck %]\"!");
+      let (Some(id), Some(name)) = (values.first(), values.get(1)) else {
+        return Err(error_message());
+      };
+      let mut psynergy_levels = Vec::new();
+      for psynergy_level in values.get(2).map_or("", String::as_str).split_whitespace() {
+        let Some((psynergy, level)) = psynergy_level.rsplit_once('@') else {
+          return Err(error_message());
+        };
+        let psynergy_id = game_data.find_psynergy_id(psynergy).map_err(|error_message| format!("{CLASS_DATA_FILE_NAME} line {line_number}: {error_message}"))?;
+        psynergy_levels.push((psynergy_id, level.parse::<u8>().map_err(|_| error_message())?));
+      }
+      let required_levels_option = match values.get(3).filter(|required_levels| !required_levels.is_empty()) {
+        Some(required_levels) => {
+          let levels = required_levels.split('/').map(|level| level.trim().parse::<u8>()).collect::<Result<Vec<u8>, _>>().map_err(|_| error_message())?;
+          Some(<[u8; 4]>::try_from(levels).map_err(|_| error_message())?)
+        }
+        None => None,
+      };
+      let base_element_indexes = values.get(4).map_or("", String::as_str).split_whitespace()
+        .map(|element| ELEMENT_LABEL.iter().position(|element_label| element_label.eq_ignore_ascii_case(element)).ok_or_else(error_message))
+        .collect::<Result<Vec<usize>, String>>()?;
+      let stat_multipliers_option = match values.get(5) {
+        Some(stat_multipliers) => Some(parse_stats(stat_multipliers).ok_or_else(error_message)?),
+        None => None,
+      };
+      game_data.classes.push(ClassData { id: u8::try_from(parse_number(id)?).map_err(|_| error_message())?, name: name.clone(), psynergy_levels, required_levels_option, base_element_indexes, stat_multipliers_option });
+    }
+
+    for (line_number, values) in read_data_file(&data_dir.join(DJINN_DATA_FILE_NAME))? {
+      let error_message = || format!("{DJINN_DATA_FILE_NAME} line {line_number}: it should be \"<djinni>, <hp/pp/attack/defense/agility/luck>\"!");
+      let [name, stat_bonuses] = &values[..] else {
+        return Err(error_message());
+      };
+      game_data.djinn.push(DjinniData { name: name.clone(), stat_bonuses: parse_stats(stat_bonuses).ok_or_else(error_message)? });
+    }
+
+    for (line_number, values) in read_data_file(&data_dir.join(GROWTH_DATA_FILE_NAME))? {
+      let error_message = || format!("{GROWTH_DATA_FILE_NAME} line {line_number}: it should be \"<character>, <level>, <hp/pp/attack/defense/agility/luck>\"!");
+      let [character_name, level, base_stats] = &values[..] else {
+        return Err(error_message());
+      };
+      let Some(pc_index) = PC_LABEL.iter().position(|pc_label| pc_label.eq_ignore_ascii_case(character_name)) else {
+        return Err(error_message());
+      };
+      game_data.growth.push(GrowthData { pc_index, level: level.parse::<u8>().map_err(|_| error_message())?, base_stats: parse_stats(base_stats).ok_or_else(error_message)? });
+    }
+
+    Ok(game_data)
+  }
+
+  pub fn find_item(&self, id: u16) -> Option<&ItemData> {
+    self.items.iter().find(|item_data| item_data.id == id)
+  }
+
+  /// Find an item by its name (case insensitive) or its ID.
+  pub fn find_item_id(&self, item: &str) -> Result<u16, String> {
+    if let Some(item_data) = self.items.iter().find(|item_data| item_data.name.eq_ignore_ascii_case(item)) {
+      return Ok(item_data.id);
+    }
+    parse_number(item).ok().and_then(|id| u16::try_from(id).ok()).ok_or_else(|| format!("Unknown item \"{item}\", use its ID or add it to {ITEM_DATA_FILE_NAME} in data directory!"))
+  }
+
+  /// Item name, or its ID if it's not in data file.
+  pub fn get_item_name(&self, id: u16) -> String {
+    self.find_item(id).map_or_else(|| format!("item {id:#05X}"), |item_data| item_data.name.clone())
+  }
+
+  /// Find psynergy by its name (case insensitive) or its ID.
+  pub fn find_psynergy_id(&self, psynergy: &str) -> Result<u16, String> {
+    if let Some(psynergy_data) = self.psynergy.iter().find(|psynergy_data| psynergy_data.name.eq_ignore_ascii_case(psynergy)) {
+      return Ok(psynergy_data.id);
+    }
+    parse_number(psynergy).ok().and_then(|id| u16::try_from(id).ok()).ok_or_else(|| format!("Unknown psynergy \"{psynergy}\", use its ID or add it to {PSYNERGY_DATA_FILE_NAME} in data directory!"))
+  }
+
+  /// Psynergy name, or its ID if it's not in data file.
+  pub fn get_psynergy_name(&self, id: u16) -> String {
+    self.psynergy.iter().find(|psynergy_data| psynergy_data.id == id).map_or_else(|| format!("psynergy {id:#05X}"), |psynergy_data| psynergy_data.name.clone())
+  }
+
+  pub fn find_class(&self, id: u8) -> Option<&ClassData> {
+    self.classes.iter().find(|class_data| class_data.id == id)
+  }
+
+  pub fn find_djinni(&self, name: &str) -> Option<&DjinniData> {
+    self.djinn.iter().find(|djinni_data| djinni_data.name.eq_ignore_ascii_case(name))
+  }
+
+  pub fn find_growth(&self, pc_index: usize, level: u8) -> Option<&GrowthData> {
+    self.growth.iter().find(|growth_data| growth_data.pc_index == pc_index && growth_data.level == level)
+  }
+
+  /// Class name, or its ID if it's not in data file.
+  pub fn get_class_name(&self, id: u8) -> String {
+    self.find_class(id).map_or_else(|| format!("class {id:#04X}"), |class_data| class_data.name.clone())
+  }
+
+  /// Add flag names, a flag that already has a name gets the new one.
+  fn add_flags(&mut self, game_type_index: usize, data_file_name: &str, lines: Vec<(usize, Vec<String>)>) -> Result<(), String> {
+    for (line_number, values) in lines {
+      let error_message = || format!("{data_file_name} line {line_number}: it should be \"<index>, <name>\"!");
+      let (Some(index), Some(_)) = (values.first(), values.get(1)) else {
+        return Err(error_message());
+      };
+      let index = u16::try_from(parse_number(index)?).map_err(|_| error_message())?;
+      // Names may contain commas.
+      let name = values[1..].join(", ");
+      match self.flags[game_type_index].iter_mut().find(|flag_data| flag_data.index == index) {
+        Some(flag_data) => flag_data.name = name,
+        None => self.flags[game_type_index].push(FlagData { index, name }),
+      }
+    }
+    self.flags[game_type_index].sort_by_key(|flag_data| flag_data.index);
+
+    Ok(())
+  }
+
+  pub fn get_flag_name(&self, index: u16, game_type_index: usize) -> Option<&str> {
+    self.flags[game_type_index].iter().find(|flag_data| flag_data.index == index).map(|flag_data| flag_data.name.as_str())
+  }
+
+  pub fn get_map_name(&self, map_id: u16, game_type_index: usize) -> Option<&str> {
+    self.maps[game_type_index].iter().find(|map_data| map_data.id == map_id).map(|map_data| map_data.name.as_str())
+  }
+
+  pub fn find_location(&self, name: &str, game_type_index: usize) -> Result<&LocationData, String> {
+    if self.locations[game_type_index].is_empty() {
+      return Err(format!("No named locations are loaded, this tool doesn't include any, write them in {} in data directory (see \"--data\"), or set map, entrance and coordinates by their IDs!", LOCATION_DATA_FILE_NAME[game_type_index]));
+    }
+    self.locations[game_type_index].iter().find(|location_data| location_data.name.eq_ignore_ascii_case(name))
+      .ok_or_else(|| format!("Unknown location \"{name}\", add it to {} in data directory, or set map, entrance and coordinates by their IDs!", LOCATION_DATA_FILE_NAME[game_type_index]))
+  }
+
+  /// Find a flag by its name (case insensitive) or its index.
+  pub fn find_flag_index(&self, flag: &str, game_type_index: usize) -> Result<u16, String> {
+    if let Some(flag_data) = self.flags[game_type_index].iter().find(|flag_data| flag_data.name.eq_ignore_ascii_case(flag)) {
+      return Ok(flag_data.index);
+    }
+    parse_number(flag).ok().and_then(|index| u16::try_from(index).ok()).ok_or_else(|| format!("Unknown flag \"{flag}\", use its index or add it to {} in data directory!", FLAG_DATA_FILE_NAME[game_type_index]))
+  }
+}
+
+/// Stats like "10/0/5/0/0/0" (HP/PP/attack/defense/agility/luck).
+fn parse_stats(stats: &str) -> Option<[i32; 6]> {
+  let values = stats.split('/').map(|value| value.trim().parse::<i32>()).collect::<Result<Vec<i32>, _>>().ok()?;
+  <[i32; 6]>::try_from(values).ok()
+}
+
+/// Read the lines of a data file, a missing file has no lines.
+fn read_data_file(path: &Path) -> Result<Vec<(usize, Vec<String>)>, String> {
+  if !path.exists() {
+    return Ok(Vec::new());
+  }
+  let Ok(text) = fs::read_to_string(path) else {
+    return Err(format!("Failed to read \"{}\"!", path.to_str().unwrap()));
+  };
+
+  Ok(parse_data_text(&text))
+}
+
+/// Split the lines of a data file into values with their line numbers, empty lines and comments are skipped.
+fn parse_data_text(text: &str) -> Vec<(usize, Vec<String>)> {
+  text.lines().enumerate().filter_map(|(i, line)| {
+    let line = line.split('#').next().unwrap().trim();
+    (!line.is_empty()).then(|| (i + 1, line.split(',').map(|value| value.trim().to_string()).collect()))
+  }).collect()
+}
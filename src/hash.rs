@@ -0,0 +1,23 @@
+/// Standard CRC-32 (IEEE 802.3), used by undo files and PNG chunks.
+pub fn crc32(bytes: &[u8]) -> u32 {
+  let mut crc = 0xFFFF_FFFFu32;
+  for byte in bytes {
+    crc ^= u32::from(*byte);
+    for _ in 0..8 {
+      crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+    }
+  }
+
+  !crc
+}
+
+/// Adler-32 checksum used by zlib streams.
+pub fn adler32(bytes: &[u8]) -> u32 {
+  let (mut a, mut b) = (1u32, 0u32);
+  for byte in bytes {
+    a = (a + u32::from(*byte)) % 65521;
+    b = (b + a) % 65521;
+  }
+
+  (b << 16) | a
+}
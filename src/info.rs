@@ -0,0 +1,118 @@
+use std::fmt::Write;
+use crate::character::{get_character_name, read_character_field, CHARACTER_FIELDS};
+use crate::class::{check_character_class, compute_class_id, describe_character_class};
+use crate::djinn::{describe_character_djinn, validate_djinn};
+use crate::field::{decode_name, describe_build_date, LEADER_NAME_LOCATION_INDEX, LEADER_NAME_SIZE, PC_LABEL};
+use crate::gamedata::GameData;
+use crate::inventory::describe_character_items;
+use crate::location::describe_location;
+use crate::party::{describe_party, is_leader_name_consistent};
+use crate::psynergy::{check_character_psynergy, describe_character_psynergy};
+use crate::save::get_live_blocks;
+use crate::status::{describe_status_value, read_status_field, STATUS_FIELDS};
+use crate::{BUILD_DATE_LOCATION_INDEX, PARTY_MEMBERS_COUNT, SAVE_SLOT_SIZE};
+
+/// Get a readable report of the save data in each slot, or only one slot.
+pub fn get_info_report(raw_save_file: &[u8], game_type_index: usize, slot_number_option: Option<u8>, game_data: &GameData) -> String {
+  let mut report = String::new();
+  for live_block in get_live_blocks(raw_save_file, game_type_index) {
+    if slot_number_option.is_some_and(|slot_number| slot_number != live_block.slot_number) {
+      continue;
+    }
+    let block_start = live_block.start(game_type_index);
+    let raw_block = &raw_save_file[block_start..block_start + SAVE_SLOT_SIZE[game_type_index]];
+
+    let build_date_location_index = BUILD_DATE_LOCATION_INDEX[game_type_index][0][0];
+    writeln!(report, "Slot {} (block {}):", live_block.slot_number, live_block.index).unwrap();
+    writeln!(report, "  Leader:     \"{}\"", decode_name(&raw_block[LEADER_NAME_LOCATION_INDEX..LEADER_NAME_LOCATION_INDEX + LEADER_NAME_SIZE])).unwrap();
+    writeln!(report, "  Party:      {}", describe_party(raw_block, game_type_index)).unwrap();
+    if !is_leader_name_consistent(raw_block, game_type_index) {
+      writeln!(report, "  Warning: The leader name is not the name of the first party member.").unwrap();
+    }
+    for status_field in &STATUS_FIELDS {
+      writeln!(report, "  {:<12}{}", format!("{}:", status_field.label), describe_status_value(status_field, read_status_field(raw_block, status_field, game_type_index))).unwrap();
+    }
+    writeln!(report, "  Location:   {}", describe_location(raw_block, game_type_index, game_data)).unwrap();
+    writeln!(report, "  Build date: {}", describe_build_date(u16::from_le_bytes([raw_block[build_date_location_index], raw_block[build_date_location_index + 1]]), game_type_index)).unwrap();
+    if !live_block.is_checksum_valid {
+      writeln!(report, "  Warning: The checksum of this block is not valid.").unwrap();
+    }
+
+    // One column for each character.
+    let pc_count = PARTY_MEMBERS_COUNT[game_type_index];
+    let label_column_width = CHARACTER_FIELDS.iter().map(|character_field| character_field.label.len() + character_field.key.len() + 4).max().unwrap();
+    write!(report, "  {:<label_column_width$}", "").unwrap();
+    for pc_label in &PC_LABEL[..pc_count] {
+      write!(report, "{pc_label:>10}").unwrap();
+    }
+    writeln!(report).unwrap();
+    write!(report, "  {:<label_column_width$}", "Name").unwrap();
+    for pc_index in 0..pc_count {
+      write!(report, "{:>10}", get_character_name(raw_block, pc_index, game_type_index)).unwrap();
+    }
+    writeln!(report).unwrap();
+    for character_field in &CHARACTER_FIELDS {
+      write!(report, "  {:<label_column_width$}", format!("{} ({})", character_field.label, character_field.key)).unwrap();
+      for pc_index in 0..pc_count {
+        write!(report, "{:>10}", read_character_field(raw_block, pc_index, character_field, game_type_index)).unwrap();
+      }
+      writeln!(report).unwrap();
+    }
+
+    writeln!(report, "  Djinn:").unwrap();
+    for (pc_index, pc_label) in PC_LABEL[..pc_count].iter().enumerate() {
+      writeln!(report, "    {:<8}{}", format!("{pc_label}:"), describe_character_djinn(raw_block, pc_index, game_type_index)).unwrap();
+    }
+    if let Err(error_message) = validate_djinn(raw_block, game_type_index) {
+      writeln!(report, "    Warning: {error_message}").unwrap();
+    }
+
+    writeln!(report, "  Class:").unwrap();
+    for (pc_index, pc_label) in PC_LABEL[..pc_count].iter().enumerate() {
+      writeln!(report, "    {:<8}{}", format!("{pc_label}:"), describe_character_class(raw_block, pc_index, game_type_index, game_data)).unwrap();
+    }
+    let mut unchecked_messages: Vec<String> = Vec::new();
+    for pc_index in 0..pc_count {
+      if let Some(warning) = check_character_class(raw_block, pc_index, game_type_index, game_data) {
+        writeln!(report, "    Warning: {warning}").unwrap();
+      }
+      // Without classes.txt the same note would be written for every character.
+      if let Err(error_message) = compute_class_id(raw_block, pc_index, game_type_index, game_data) {
+        if !unchecked_messages.contains(&error_message) {
+          writeln!(report, "    Note: {error_message}").unwrap();
+          unchecked_messages.push(error_message);
+        }
+      }
+    }
+
+    writeln!(report, "  Items:").unwrap();
+    for (pc_index, pc_label) in PC_LABEL[..pc_count].iter().enumerate() {
+      writeln!(report, "    {:<8}{}", format!("{pc_label}:"), describe_character_items(raw_block, pc_index, game_type_index, game_data)).unwrap();
+    }
+
+    writeln!(report, "  Psynergy:").unwrap();
+    for (pc_index, pc_label) in PC_LABEL[..pc_count].iter().enumerate() {
+      writeln!(report, "    {:<8}{}", format!("{pc_label}:"), describe_character_psynergy(raw_block, pc_index, game_type_index, game_data)).unwrap();
+    }
+    unchecked_messages.clear();
+    for pc_index in 0..pc_count {
+      match check_character_psynergy(raw_block, pc_index, game_type_index, game_data) {
+        Ok(warnings) => {
+          for warning in warnings {
+            writeln!(report, "    Warning: {warning}").unwrap();
+          }
+        }
+        Err(error_message) if !unchecked_messages.contains(&error_message) => {
+          writeln!(report, "    Note: {error_message}").unwrap();
+          unchecked_messages.push(error_message);
+        }
+        Err(_) => {}
+      }
+    }
+  }
+  if report.is_empty() {
+    writeln!(report, "There is no save data to show.").unwrap();
+  }
+
+  report
+}
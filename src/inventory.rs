@@ -0,0 +1,276 @@
+use std::fmt::Write;
+use crate::character::get_character_offset;
+use crate::edit::parse_number;
+use crate::field::PC_LABEL;
+use crate::gamedata::{GameData, ItemType, ITEM_DATA_FILE_NAME};
+
+/* Items in character data, 15 slots of u16 at 0xD8.
+   Bits 0 - 8    Item ID, 0 is an empty slot
+   Bit 9         Equipped
+   Bit 10        Broken
+   Bits 11 - 15  Quantity - 1, only stackable items have more than one */
+const ITEM_SLOT_OFFSET: usize = 0xD8;
+pub const ITEM_SLOT_COUNT: usize = 15;
+const ITEM_ID_MASK: u16 = 0x1FF;
+const ITEM_EQUIPPED_BIT: u16 = 0x200;
+const ITEM_BROKEN_BIT: u16 = 0x400;
+const ITEM_QUANTITY_SHIFT: u32 = 11;
+const MAX_ITEM_QUANTITY: u16 = 30;
+
+#[derive(Clone, Copy)]
+struct ItemSlot {
+  id: u16,
+  is_equipped: bool,
+  is_broken: bool,
+  quantity: u16,
+}
+
+impl ItemSlot {
+  const EMPTY: Self = Self { id: 0, is_equipped: false, is_broken: false, quantity: 1 };
+
+  fn is_empty(self) -> bool {
+    self.id == 0
+  }
+
+  fn describe(self, game_data: &GameData) -> String {
+    if self.is_empty() {
+      return String::from("empty");
+    }
+    let mut description = game_data.get_item_name(self.id);
+    if self.quantity > 1 {
+      write!(description, " x{}", self.quantity).unwrap();
+    }
+    if self.is_equipped {
+      description.push_str(" (equipped)");
+    }
+    if game_data.find_item(self.id).is_some_and(|item_data| item_data.is_cursed) {
+      description.push_str(" (cursed)");
+    }
+    if self.is_broken {
+      description.push_str(" (broken)");
+    }
+
+    description
+  }
+}
+
+fn read_item_slot(raw_block: &[u8], pc_index: usize, slot_index: usize, game_type_index: usize) -> ItemSlot {
+  let location_index = get_character_offset(pc_index, game_type_index) + ITEM_SLOT_OFFSET + slot_index * 2;
+  let value = u16::from_le_bytes([raw_block[location_index], raw_block[location_index + 1]]);
+
+  ItemSlot {
+    id: value & ITEM_ID_MASK,
+    is_equipped: value & ITEM_EQUIPPED_BIT != 0,
+    is_broken: value & ITEM_BROKEN_BIT != 0,
+    quantity: (value >> ITEM_QUANTITY_SHIFT) + 1,
+  }
+}
+
+fn write_item_slot(raw_block: &mut [u8], pc_index: usize, slot_index: usize, item_slot: ItemSlot, game_type_index: usize) {
+  let value = if item_slot.is_empty() {
+    0
+  } else {
+    item_slot.id | if item_slot.is_equipped { ITEM_EQUIPPED_BIT } else { 0 } | if item_slot.is_broken { ITEM_BROKEN_BIT } else { 0 } | (item_slot.quantity - 1) << ITEM_QUANTITY_SHIFT
+  };
+  let location_index = get_character_offset(pc_index, game_type_index) + ITEM_SLOT_OFFSET + slot_index * 2;
+  raw_block[location_index..location_index + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+/// IDs of the items a character carries.
+pub fn get_character_item_ids(raw_block: &[u8], pc_index: usize, game_type_index: usize) -> Vec<u16> {
+  (0..ITEM_SLOT_COUNT).map(|slot_index| read_item_slot(raw_block, pc_index, slot_index, game_type_index)).filter(|item_slot| !item_slot.is_empty()).map(|item_slot| item_slot.id).collect()
+}
+
+/// IDs of the items a character has equipped.
+pub fn get_equipped_item_ids(raw_block: &[u8], pc_index: usize, game_type_index: usize) -> Vec<u16> {
+  (0..ITEM_SLOT_COUNT).map(|slot_index| read_item_slot(raw_block, pc_index, slot_index, game_type_index)).filter(|item_slot| !item_slot.is_empty() && item_slot.is_equipped).map(|item_slot| item_slot.id).collect()
+}
+
+/// Items of a character, like "1. Long Sword (equipped), 2. Herb x3".
+pub fn describe_character_items(raw_block: &[u8], pc_index: usize, game_type_index: usize, game_data: &GameData) -> String {
+  let descriptions: Vec<String> = (0..ITEM_SLOT_COUNT)
+    .map(|slot_index| (slot_index, read_item_slot(raw_block, pc_index, slot_index, game_type_index)))
+    .filter(|(_, item_slot)| !item_slot.is_empty())
+    .map(|(slot_index, item_slot)| format!("{}. {}", slot_index + 1, item_slot.describe(game_data)))
+    .collect();
+
+  if descriptions.is_empty() { String::from("none") } else { descriptions.join(", ") }
+}
+
+/* "<character>.item.<...>=<value>":
+   item.add=<item>[x<quantity>]    Put an item into the first empty slot
+   item.<slot>=<item>[x<quantity>] Replace the item in a slot (1 - 15), "none" empties it
+   item.<slot>.quantity=<number>   Change the quantity of a stackable item
+   item.<slot>.equipped=yes|no     Equip or unequip an item */
+pub fn assign_character_item(raw_block: &mut [u8], pc_index: usize, key_parts: &[&str], value: &str, game_type_index: usize, game_data: &GameData) -> Result<String, String> {
+  let (slot_index, property_option) = match key_parts {
+    ["add"] => {
+      let slot_index = (0..ITEM_SLOT_COUNT).find(|slot_index| read_item_slot(raw_block, pc_index, *slot_index, game_type_index).is_empty())
+        .ok_or_else(|| format!("{} can't carry more than {ITEM_SLOT_COUNT} items!", PC_LABEL[pc_index]))?;
+      (slot_index, None)
+    }
+    [slot] => (parse_slot_index(slot)?, None),
+    [slot, property] => (parse_slot_index(slot)?, Some(*property)),
+    _ => return Err(String::from("Unknown item target, it should be like \"item.add\", \"item.<slot>\", \"item.<slot>.quantity\" or \"item.<slot>.equipped\"!")),
+  };
+
+  let old_item_slot = read_item_slot(raw_block, pc_index, slot_index, game_type_index);
+  let mut new_item_slot = old_item_slot;
+  match property_option {
+    None if value.eq_ignore_ascii_case("none") => new_item_slot = ItemSlot::EMPTY,
+    None => {
+      let (id, quantity) = parse_item_with_quantity(value, game_data)?;
+      new_item_slot = ItemSlot { id, is_equipped: false, is_broken: false, quantity };
+    }
+    Some(_) if old_item_slot.is_empty() => return Err(format!("Item slot {} is empty!", slot_index + 1)),
+    Some("quantity") => new_item_slot.quantity = u16::try_from(parse_number(value)?).map_err(|_| format!("Invalid quantity \"{value}\"!"))?,
+    Some("equipped") => new_item_slot.is_equipped = parse_yes_no(value)?,
+    Some(property) => return Err(format!("Unknown item property \"{property}\", it should be \"quantity\" or \"equipped\"!")),
+  }
+
+  check_item_slot(raw_block, pc_index, slot_index, new_item_slot, game_type_index, game_data)?;
+  write_item_slot(raw_block, pc_index, slot_index, new_item_slot, game_type_index);
+
+  Ok(format!("{}'s item slot {}: {} -> {}", PC_LABEL[pc_index], slot_index + 1, old_item_slot.describe(game_data), new_item_slot.describe(game_data)))
+}
+
+/// "<item>" or "<item>x<quantity>", the whole value is tried as an item first, so "0x12" is item 0x12, not 12 of item 0.
+fn parse_item_with_quantity(value: &str, game_data: &GameData) -> Result<(u16, u16), String> {
+  let item_error = match game_data.find_item_id(value) {
+    Ok(id) => return Ok((id, 1)),
+    Err(error_message) => error_message,
+  };
+  let Some((item, quantity)) = value.rsplit_once(['x', 'X']).and_then(|(item, quantity)| Some((item.trim(), quantity.trim().parse::<u16>().ok()?))) else {
+    return Err(item_error);
+  };
+
+  Ok((game_data.find_item_id(item)?, quantity))
+}
+
+/// Check the limits of the game.
+/// Quantities and equipment need the item data, so they are refused for the items that are not in data file.
+fn check_item_slot(raw_block: &[u8], pc_index: usize, slot_index: usize, item_slot: ItemSlot, game_type_index: usize, game_data: &GameData) -> Result<(), String> {
+  if item_slot.is_empty() {
+    return Ok(());
+  }
+  if item_slot.id > ITEM_ID_MASK {
+    return Err(format!("Item ID can't be more than {ITEM_ID_MASK:#05X}!"));
+  }
+  if item_slot.quantity == 0 || item_slot.quantity > MAX_ITEM_QUANTITY {
+    return Err(format!("Quantity must be between 1 and {MAX_ITEM_QUANTITY}!"));
+  }
+
+  let Some(item_data) = game_data.find_item(item_slot.id) else {
+    if item_slot.quantity > 1 || item_slot.is_equipped {
+      return Err(format!("{} is not in {ITEM_DATA_FILE_NAME}, its quantity and equipment can't be checked, please add it to the data file and use \"--data\"!", game_data.get_item_name(item_slot.id)));
+    }
+    return Ok(());
+  };
+  if item_slot.quantity > 1 && !item_data.is_stackable {
+    return Err(format!("{} is not stackable!", item_data.name));
+  }
+  if item_slot.is_equipped {
+    if item_data.item_type == ItemType::Other {
+      return Err(format!("{} can't be equipped!", item_data.name));
+    }
+    // One equipped item for each equipment type.
+    for other_slot_index in (0..ITEM_SLOT_COUNT).filter(|other_slot_index| *other_slot_index != slot_index) {
+      let other_item_slot = read_item_slot(raw_block, pc_index, other_slot_index, game_type_index);
+      if other_item_slot.is_empty() || !other_item_slot.is_equipped {
+        continue;
+      }
+      let Some(other_item_data) = game_data.find_item(other_item_slot.id) else {
+        return Err(format!("{} has equipped {}, which is not in {ITEM_DATA_FILE_NAME}, its equipment type can't be checked!", PC_LABEL[pc_index], game_data.get_item_name(other_item_slot.id)));
+      };
+      if other_item_data.item_type == item_data.item_type {
+        return Err(format!("{} has already equipped {} ({}), unequip it first!", PC_LABEL[pc_index], game_data.get_item_name(other_item_slot.id), item_data.item_type.label()));
+      }
+    }
+  }
+
+  Ok(())
+}
+
+fn parse_slot_index(slot: &str) -> Result<usize, String> {
+  match slot.parse::<usize>() {
+    Ok(slot_number) if (1..=ITEM_SLOT_COUNT).contains(&slot_number) => Ok(slot_number - 1),
+    _ => Err(format!("Invalid item slot \"{slot}\", it should be between 1 and {ITEM_SLOT_COUNT}!")),
+  }
+}
+
+fn parse_yes_no(value: &str) -> Result<bool, String> {
+  match value.to_ascii_lowercase().as_str() {
+    "yes" | "true" | "1" => Ok(true),
+    "no" | "false" | "0" => Ok(false),
+    _ => Err(format!("Invalid value \"{value}\", it should be \"yes\" or \"no\"!")),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::gamedata::ItemData;
+  use crate::SAVE_SLOT_SIZE;
+
+  fn create_item_data(id: u16, name: &str, item_type: ItemType, is_stackable: bool) -> ItemData {
+    ItemData { id, name: String::from(name), item_type, is_stackable, is_cursed: false, is_class_item: false, psynergy_id_option: None, stat_bonuses_option: None }
+  }
+
+  fn create_game_data() -> GameData {
+    GameData {
+      items: vec![create_item_data(0x001, "Long Sword", ItemType::Weapon, false), create_item_data(0x002, "Broad Sword", ItemType::Weapon, false), create_item_data(0x0B4, "Herb", ItemType::Other, true)],
+      ..GameData::default()
+    }
+  }
+
+  #[test]
+  fn hex_item_id_is_not_a_quantity() {
+    let game_data = GameData::default();
+    assert_eq!(parse_item_with_quantity("0x12", &game_data), Ok((0x12, 1)));
+    assert_eq!(parse_item_with_quantity("0X1B4", &game_data), Ok((0x1B4, 1)));
+    assert_eq!(parse_item_with_quantity("0x12x3", &game_data), Ok((0x12, 3)));
+    assert_eq!(parse_item_with_quantity("0x12 x 3", &game_data), Ok((0x12, 3)));
+    assert_eq!(parse_item_with_quantity("18X2", &game_data), Ok((18, 2)));
+    assert!(parse_item_with_quantity("Herb", &game_data).is_err());
+  }
+
+  #[test]
+  fn item_names_with_quantity() {
+    let game_data = create_game_data();
+    assert_eq!(parse_item_with_quantity("herb", &game_data), Ok((0x0B4, 1)));
+    assert_eq!(parse_item_with_quantity("Herb x5", &game_data), Ok((0x0B4, 5)));
+    assert_eq!(parse_item_with_quantity("0xB4x5", &game_data), Ok((0x0B4, 5)));
+    assert!(parse_item_with_quantity("Herb x", &game_data).is_err());
+  }
+
+  #[test]
+  fn quantity_and_equipment_need_item_data() {
+    let raw_block = vec![0u8; SAVE_SLOT_SIZE[1]];
+    let game_data = create_game_data();
+    let unknown_item_slot = ItemSlot { id: 0x012, is_equipped: false, is_broken: false, quantity: 1 };
+    assert!(check_item_slot(&raw_block, 0, 0, unknown_item_slot, 1, &game_data).is_ok());
+    assert!(check_item_slot(&raw_block, 0, 0, ItemSlot { quantity: 2, ..unknown_item_slot }, 1, &game_data).is_err());
+    assert!(check_item_slot(&raw_block, 0, 0, ItemSlot { is_equipped: true, ..unknown_item_slot }, 1, &game_data).is_err());
+
+    let herb_item_slot = ItemSlot { id: 0x0B4, is_equipped: false, is_broken: false, quantity: 30 };
+    assert!(check_item_slot(&raw_block, 0, 0, herb_item_slot, 1, &game_data).is_ok());
+    assert!(check_item_slot(&raw_block, 0, 0, ItemSlot { quantity: 31, ..herb_item_slot }, 1, &game_data).is_err());
+    assert!(check_item_slot(&raw_block, 0, 0, ItemSlot { is_equipped: true, ..herb_item_slot }, 1, &game_data).is_err());
+    let sword_item_slot = ItemSlot { id: 0x001, is_equipped: false, is_broken: false, quantity: 2 };
+    assert!(check_item_slot(&raw_block, 0, 0, sword_item_slot, 1, &game_data).is_err());
+  }
+
+  #[test]
+  fn one_equipped_item_for_each_type() {
+    let mut raw_block = vec![0u8; SAVE_SLOT_SIZE[1]];
+    let game_data = create_game_data();
+    write_item_slot(&mut raw_block, 0, 0, ItemSlot { id: 0x001, is_equipped: true, is_broken: false, quantity: 1 }, 1);
+    let broad_sword_item_slot = ItemSlot { id: 0x002, is_equipped: true, is_broken: false, quantity: 1 };
+    assert!(check_item_slot(&raw_block, 0, 1, broad_sword_item_slot, 1, &game_data).is_err());
+    assert!(check_item_slot(&raw_block, 0, 0, broad_sword_item_slot, 1, &game_data).is_ok());
+
+    // An equipped item that is not in data file can't be compared.
+    write_item_slot(&mut raw_block, 0, 0, ItemSlot { id: 0x012, is_equipped: true, is_broken: false, quantity: 1 }, 1);
+    assert!(check_item_slot(&raw_block, 0, 1, broad_sword_item_slot, 1, &game_data).is_err());
+  }
+}
@@ -0,0 +1,404 @@
+use std::fmt::Write;
+
+/* Export and import only need integers, strings, arrays and objects, so a small parser is kept here
+   instead of adding a dependency, the tool only depends on clap. It follows JSON strictly (RFC 8259) for what it supports,
+   malformed input is refused with its position, and the values are checked again by import. */
+
+/// A minimal JSON value, enough for exporting and importing save files.
+/// Object keys keep their order, so the exported file is easy to read.
+pub enum JsonValue {
+  Null,
+  Bool(bool),
+  Number(i64),
+  String(String),
+  Array(Vec<JsonValue>),
+  Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+  pub fn get(&self, key: &str) -> Option<&JsonValue> {
+    match self {
+      Self::Object(members) => members.iter().find(|(member_key, _)| member_key == key).map(|(_, value)| value),
+      _ => None,
+    }
+  }
+
+  pub fn as_i64(&self) -> Option<i64> {
+    match self {
+      Self::Number(value) => Some(*value),
+      _ => None,
+    }
+  }
+
+  pub fn as_bool(&self) -> Option<bool> {
+    match self {
+      Self::Bool(value) => Some(*value),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Self::String(value) => Some(value),
+      _ => None,
+    }
+  }
+
+  pub fn as_array(&self) -> Option<&[JsonValue]> {
+    match self {
+      Self::Array(values) => Some(values),
+      _ => None,
+    }
+  }
+
+  /// Serialize with 2 spaces indentation.
+  pub fn to_pretty_string(&self) -> String {
+    let mut output = String::new();
+    self.write_pretty(&mut output, 0);
+    output.push('\n');
+    output
+  }
+
+  fn write_pretty(&self, output: &mut String, indent: usize) {
+    match self {
+      Self::Null => output.push_str("null"),
+      Self::Bool(value) => write!(output, "{value}").unwrap(),
+      Self::Number(value) => write!(output, "{value}").unwrap(),
+      Self::String(value) => write_json_string(output, value),
+      Self::Array(values) => {
+        // Short arrays of numbers and strings are kept in one line.
+        if values.iter().all(|value| !matches!(value, Self::Array(_) | Self::Object(_))) {
+          output.push('[');
+          for (i, value) in values.iter().enumerate() {
+            if i > 0 {
+              output.push_str(", ");
+            }
+            value.write_pretty(output, indent);
+          }
+          output.push(']');
+          return;
+        }
+        output.push_str("[\n");
+        for (i, value) in values.iter().enumerate() {
+          output.push_str(&" ".repeat(indent + 2));
+          value.write_pretty(output, indent + 2);
+          output.push_str(if i + 1 < values.len() { ",\n" } else { "\n" });
+        }
+        output.push_str(&" ".repeat(indent));
+        output.push(']');
+      }
+      Self::Object(members) => {
+        if members.is_empty() {
+          output.push_str("{}");
+          return;
+        }
+        output.push_str("{\n");
+        for (i, (key, value)) in members.iter().enumerate() {
+          output.push_str(&" ".repeat(indent + 2));
+          write_json_string(output, key);
+          output.push_str(": ");
+          value.write_pretty(output, indent + 2);
+          output.push_str(if i + 1 < members.len() { ",\n" } else { "\n" });
+        }
+        output.push_str(&" ".repeat(indent));
+        output.push('}');
+      }
+    }
+  }
+}
+
+fn write_json_string(output: &mut String, value: &str) {
+  output.push('"');
+  for c in value.chars() {
+    match c {
+      '"' => output.push_str("\\\""),
+      '\\' => output.push_str("\\\\"),
+      '\n' => output.push_str("\\n"),
+      '\r' => output.push_str("\\r"),
+      '\t' => output.push_str("\\t"),
+      c if u32::from(c) < 0x20 => write!(output, "\\u{:04x}", u32::from(c)).unwrap(),
+      c => output.push(c),
+    }
+  }
+  output.push('"');
+}
+
+/// Exported files are only a few levels deep, deeper ones are refused instead of overflowing the stack.
+const MAX_NESTING_DEPTH: usize = 64;
+
+/// Parse a JSON text, only integer numbers are supported.
+pub fn parse_json(text: &str) -> Result<JsonValue, String> {
+  let mut parser = JsonParser { chars: text.chars().collect(), position: 0, depth: 0 };
+  let value = parser.parse_value()?;
+  parser.skip_whitespace();
+  if parser.position != parser.chars.len() {
+    return Err(parser.error("Unexpected trailing characters"));
+  }
+
+  Ok(value)
+}
+
+struct JsonParser {
+  chars: Vec<char>,
+  position: usize,
+  // Arrays and objects the parser is in.
+  depth: usize,
+}
+
+impl JsonParser {
+  fn error(&self, message: &str) -> String {
+    format!("{message} at character {} in JSON file!", self.position)
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.position).copied()
+  }
+
+  fn skip_whitespace(&mut self) {
+    // Only the whitespace of JSON, not every Unicode space.
+    while self.peek().is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r')) {
+      self.position += 1;
+    }
+  }
+
+  fn expect(&mut self, expected: char) -> Result<(), String> {
+    self.skip_whitespace();
+    if self.peek() != Some(expected) {
+      return Err(self.error(&format!("Expected '{expected}'")));
+    }
+    self.position += 1;
+    Ok(())
+  }
+
+  fn parse_literal(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue, String> {
+    if self.chars[self.position..].iter().take(literal.len()).copied().eq(literal.chars()) {
+      self.position += literal.len();
+      Ok(value)
+    } else {
+      Err(self.error("Invalid value"))
+    }
+  }
+
+  fn parse_value(&mut self) -> Result<JsonValue, String> {
+    if self.depth == MAX_NESTING_DEPTH {
+      return Err(self.error("Too deeply nested"));
+    }
+    self.depth += 1;
+    let value = self.parse_value_in_depth();
+    self.depth -= 1;
+
+    value
+  }
+
+  fn parse_value_in_depth(&mut self) -> Result<JsonValue, String> {
+    self.skip_whitespace();
+    match self.peek() {
+      Some('n') => self.parse_literal("null", JsonValue::Null),
+      Some('t') => self.parse_literal("true", JsonValue::Bool(true)),
+      Some('f') => self.parse_literal("false", JsonValue::Bool(false)),
+      Some('"') => Ok(JsonValue::String(self.parse_string()?)),
+      Some('[') => {
+        self.position += 1;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+          self.position += 1;
+          return Ok(JsonValue::Array(values));
+        }
+        loop {
+          values.push(self.parse_value()?);
+          self.skip_whitespace();
+          match self.peek() {
+            Some(',') => self.position += 1,
+            Some(']') => {
+              self.position += 1;
+              return Ok(JsonValue::Array(values));
+            }
+            _ => return Err(self.error("Expected ',' or ']'")),
+          }
+        }
+      }
+      Some('{') => {
+        self.position += 1;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+          self.position += 1;
+          return Ok(JsonValue::Object(members));
+        }
+        loop {
+          self.skip_whitespace();
+          let key = self.parse_string()?;
+          self.expect(':')?;
+          members.push((key, self.parse_value()?));
+          self.skip_whitespace();
+          match self.peek() {
+            Some(',') => self.position += 1,
+            Some('}') => {
+              self.position += 1;
+              return Ok(JsonValue::Object(members));
+            }
+            _ => return Err(self.error("Expected ',' or '}'")),
+          }
+        }
+      }
+      Some(c) if c == '-' || c.is_ascii_digit() => {
+        let start = self.position;
+        if c == '-' {
+          self.position += 1;
+        }
+        let digits_start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+          self.position += 1;
+        }
+        // Like JSON, "-" needs digits and there are no leading zeros, "012" is not read as 12.
+        let digit_count = self.position - digits_start;
+        if digit_count == 0 || (digit_count > 1 && self.chars[digits_start] == '0') {
+          return Err(self.error("Invalid number"));
+        }
+        let number: String = self.chars[start..self.position].iter().collect();
+        number.parse::<i64>().map(JsonValue::Number).map_err(|_| self.error("Number out of range"))
+      }
+      _ => Err(self.error("Invalid value")),
+    }
+  }
+
+  fn parse_string(&mut self) -> Result<String, String> {
+    if self.peek() != Some('"') {
+      return Err(self.error("Expected string"));
+    }
+    self.position += 1;
+
+    let mut value = String::new();
+    loop {
+      let Some(c) = self.peek() else {
+        return Err(self.error("Unterminated string"));
+      };
+      self.position += 1;
+      match c {
+        '"' => return Ok(value),
+        '\\' => {
+          let Some(escaped) = self.peek() else {
+            return Err(self.error("Unterminated string"));
+          };
+          self.position += 1;
+          match escaped {
+            '"' | '\\' | '/' => value.push(escaped),
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            'b' => value.push('\u{8}'),
+            'f' => value.push('\u{C}'),
+            'u' => {
+              let mut code_point = self.parse_unicode_escape_digits()?;
+              // Characters outside the BMP are escaped as a surrogate pair, like "\ud83d\ude00".
+              if (0xD800..0xDC00).contains(&code_point) {
+                if self.chars.get(self.position..self.position + 2) != Some(&['\\', 'u']) {
+                  return Err(self.error("Invalid unicode escape"));
+                }
+                self.position += 2;
+                let low_surrogate = self.parse_unicode_escape_digits()?;
+                if !(0xDC00..0xE000).contains(&low_surrogate) {
+                  return Err(self.error("Invalid unicode escape"));
+                }
+                code_point = 0x10000 + ((code_point - 0xD800) << 10) + (low_surrogate - 0xDC00);
+              }
+              value.push(char::from_u32(code_point).ok_or_else(|| self.error("Invalid unicode escape"))?);
+            }
+            _ => return Err(self.error("Invalid escape")),
+          }
+        }
+        // Control characters must be escaped in JSON strings.
+        c if u32::from(c) < 0x20 => {
+          self.position -= 1;
+          return Err(self.error("Unescaped control character in string"));
+        }
+        c => value.push(c),
+      }
+    }
+  }
+
+  /// The 4 hex digits after "\u".
+  fn parse_unicode_escape_digits(&mut self) -> Result<u32, String> {
+    let Some(hex_digits) = self.chars.get(self.position..self.position + 4).filter(|hex_digits| hex_digits.iter().all(char::is_ascii_hexdigit)) else {
+      return Err(self.error("Invalid unicode escape"));
+    };
+    let code_point = hex_digits.iter().fold(0, |code_point, hex_digit| code_point * 16 + hex_digit.to_digit(16).unwrap());
+    self.position += 4;
+
+    Ok(code_point)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_escapes() {
+    let value = parse_json(r#""a\"\\\/\n\r\t\b\f\u00e9\ud83d\ude00""#).unwrap();
+    assert_eq!(value.as_str(), Some("a\"\\/\n\r\t\u{8}\u{C}\u{e9}\u{1F600}"));
+  }
+
+  #[test]
+  fn parse_invalid_escapes() {
+    for text in [r#""\x""#, r#""\u12""#, r#""\u+123""#, r#""\ud83d""#, r#""\ud83d\u0041""#, r#""\ude00""#, r#""abc"#, r#""\"#] {
+      assert!(parse_json(text).is_err(), "{text}");
+    }
+  }
+
+  #[test]
+  fn parse_numbers() {
+    assert_eq!(parse_json("0").unwrap().as_i64(), Some(0));
+    assert_eq!(parse_json("-0").unwrap().as_i64(), Some(0));
+    assert_eq!(parse_json(" -42 ").unwrap().as_i64(), Some(-42));
+    assert_eq!(parse_json("9223372036854775807").unwrap().as_i64(), Some(i64::MAX));
+    assert_eq!(parse_json("-9223372036854775808").unwrap().as_i64(), Some(i64::MIN));
+    for text in ["-", "--1", "-a", "9223372036854775808", "-9223372036854775809", "99999999999999999999999", "1.5", "1e3", "+1", "0x10", "012", "-01", "00"] {
+      assert!(parse_json(text).is_err(), "{text}");
+    }
+    assert!(parse_json("9223372036854775808").is_err_and(|error_message| error_message.starts_with("Number out of range")));
+  }
+
+  #[test]
+  fn parse_unicode() {
+    assert_eq!(parse_json("\"ガルシア \\u00E9\\u00e9 \u{1F600}\"").unwrap().as_str(), Some("ガルシア éé \u{1F600}"));
+    assert_eq!(parse_json(r#""\u0000\u001f""#).unwrap().as_str(), Some("\u{0}\u{1F}"));
+    // Control characters must be escaped.
+    for text in ["\"a\nb\"", "\"\t\"", "\"\u{0}\""] {
+      assert!(parse_json(text).is_err(), "{text:?}");
+    }
+    // Swapped surrogates, a high surrogate followed by something else, and escapes with non-hex or too few digits.
+    for text in [r#""\udc00\ud800""#, r#""\uD800x""#, r#""\uD800\n""#, r#""\u00G0""#, r#""\u""#, r#""\u00e""#] {
+      assert!(parse_json(text).is_err(), "{text}");
+    }
+  }
+
+  #[test]
+  fn parse_malformed_input() {
+    for text in ["", "\u{A0}1", "[\u{3000}]", "[", "[1,", "[1 2]", "{\"a\" 1}", "{\"a\": 1,}", "{1: 2}", "tru", "nul", "[] []", "{\"a\": }"] {
+      assert!(parse_json(text).is_err(), "{text}");
+    }
+  }
+
+  #[test]
+  fn parse_nesting_depth_limit() {
+    let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    assert!(parse_json(&nested(MAX_NESTING_DEPTH)).is_ok());
+    assert!(parse_json(&nested(MAX_NESTING_DEPTH + 1)).is_err());
+    assert!(parse_json(&"[".repeat(1_000_000)).is_err());
+  }
+
+  #[test]
+  fn pretty_string_round_trip() {
+    let value = JsonValue::Object(vec![
+      (String::from("name"), JsonValue::String(String::from("Isaac \"\u{1}\" \u{1F600}"))),
+      (String::from("values"), JsonValue::Array(vec![JsonValue::Number(-1), JsonValue::Bool(true), JsonValue::Null])),
+      (String::from("empty"), JsonValue::Object(Vec::new())),
+    ]);
+    let text = value.to_pretty_string();
+    let parsed_value = parse_json(&text).unwrap();
+    assert_eq!(parsed_value.to_pretty_string(), text);
+    assert_eq!(parsed_value.get("name").and_then(JsonValue::as_str), Some("Isaac \"\u{1}\" \u{1F600}"));
+  }
+}
@@ -0,0 +1,64 @@
+use std::fmt::Write;
+use crate::edit::parse_number;
+use crate::gamedata::GameData;
+
+/* The place a save resumes at, from the Golden Sun Hacking Community RAM map (block offset = RAM offset + 0x10).
+   0x410  Map ID (u16)
+   0x412  Entrance ID (u16), the door or road the party entered the map from
+   0x414  X, Y coordinates (u16)
+
+   A save made in a sanctum after a build date mismatch, or in a place where the party is stuck,
+   can be moved to a safe place like a sanctum or a town entrance.
+   No map names or named places are built in, there is no verified list of them to ship,
+   they come from the map and location data files the user writes, like from "info" of a save made at that place. */
+pub const LOCATION_LOCATION_INDEX: [usize; 2] = [0x410, 0x410];
+
+/// (key, label, offset from the start of location)
+const LOCATION_PARTS: [(&str, &str, usize); 4] = [("map", "Map", 0), ("entrance", "Entrance", 2), ("x", "X", 4), ("y", "Y", 6)];
+
+fn read_location_part(raw_block: &[u8], part_offset: usize, game_type_index: usize) -> u16 {
+  let location_index = LOCATION_LOCATION_INDEX[game_type_index] + part_offset;
+  u16::from_le_bytes([raw_block[location_index], raw_block[location_index + 1]])
+}
+
+fn write_location_part(raw_block: &mut [u8], part_offset: usize, value: u16, game_type_index: usize) {
+  let location_index = LOCATION_LOCATION_INDEX[game_type_index] + part_offset;
+  raw_block[location_index..location_index + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Like "map 0x0012 (Vale), entrance 2, x 100, y 200".
+pub fn describe_location(raw_block: &[u8], game_type_index: usize, game_data: &GameData) -> String {
+  let map_id = read_location_part(raw_block, 0, game_type_index);
+  let mut description = match game_data.get_map_name(map_id, game_type_index) {
+    Some(map_name) => format!("map {map_id:#06X} ({map_name})"),
+    None => format!("map {map_id:#06X}"),
+  };
+  for (key, _, part_offset) in &LOCATION_PARTS[1..] {
+    write!(description, ", {key} {}", read_location_part(raw_block, *part_offset, game_type_index)).unwrap();
+  }
+
+  description
+}
+
+/* "location=<name>" moves the save to a named location in data file,
+   "location.map=<id>", "location.entrance=<id>", "location.x=<value>" and "location.y=<value>" set the raw values. */
+pub fn assign_location(raw_block: &mut [u8], part_key_option: Option<&str>, value: &str, game_type_index: usize, game_data: &GameData) -> Result<String, String> {
+  let old_description = describe_location(raw_block, game_type_index, game_data);
+  match part_key_option {
+    None => {
+      let location_data = game_data.find_location(value, game_type_index)?;
+      for (part_offset, part_value) in [location_data.map_id, location_data.entrance_id, location_data.x, location_data.y].iter().enumerate() {
+        write_location_part(raw_block, part_offset * 2, *part_value, game_type_index);
+      }
+    }
+    Some(part_key) => {
+      let Some((_, label, part_offset)) = LOCATION_PARTS.iter().find(|(key, _, _)| key.eq_ignore_ascii_case(part_key)) else {
+        return Err(format!("Unknown location part \"{part_key}\", it should be one of {}!", LOCATION_PARTS.iter().map(|(key, _, _)| *key).collect::<Vec<&str>>().join(", ")));
+      };
+      let part_value = u16::try_from(parse_number(value)?).map_err(|_| format!("{label} can't be more than {}!", u16::MAX))?;
+      write_location_part(raw_block, *part_offset, part_value, game_type_index);
+    }
+  }
+
+  Ok(format!("Location: {old_description} -> {}", describe_location(raw_block, game_type_index, game_data)))
+}
@@ -1,10 +1,35 @@
+mod backup;
+mod batch;
+mod character;
+mod class;
+mod container;
+mod diff;
+mod djinn;
+mod edit;
+mod export;
+mod field;
+mod flags;
+mod gamedata;
+mod hash;
+mod info;
+mod inventory;
+mod json;
+mod location;
+mod party;
+mod preview;
+mod psynergy;
+mod save;
+mod stats;
+mod status;
+mod undo;
+
 use std::fs;
-use std::fs::File;
-use std::io::{Read, Write};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::string::String;
-use clap::{Command, arg, ArgGroup, value_parser, ValueEnum, crate_version};
+use clap::{Command, arg, ArgGroup, ArgMatches, value_parser, ValueEnum, crate_version};
 use clap::builder::PossibleValue;
+use crate::container::{SaveContainer, WrapFormat};
 
 /// 7 bytes for the ASCII string "CAMELOT" in each save's header.
 const HEADER_CAMELOT_ASCII_STRING: &str = "CAMELOT";
@@ -98,6 +123,9 @@ const BUILD_DATE_LOCATION_INDEX: [[[usize; 2]; 3]; 2] = [
 const CHECKSUM_RANGE: [usize; 2] = [0xFF0, 0x2FF0];
 const HEADER_CHECKSUM_LOCATION_INDEX: [usize; 2] = [0x08, 0x09];
 
+/// In the case where multiple headers have the same slot number, the one with the highest priority number is used by game.
+const HEADER_PRIORITY_LOCATION_INDEX: [usize; 2] = [0x0A, 0x0B];
+
 #[derive(Clone, Copy)]
 enum GameType {
   // GS1
@@ -146,6 +174,7 @@ impl ValueEnum for NameType {
   }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy)]
 enum BuildDateType {
   Japan,
@@ -190,6 +219,187 @@ impl ValueEnum for BuildDateType {
   }
 }
 
+/// How playable characters' names are encoded in the game ROM.
+/// A name written with one encoding will be displayed as garbage in a ROM that uses another one.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TextEncoding {
+  // Half-width katakana, Japanese version and Korean fan translation
+  Japanese,
+  // ASCII with a few accented letters, all western versions
+  Latin,
+  // 2 bytes per character, Chinese fan translation by Mobile Team
+  ChineseMobileTeam,
+  // 2 bytes per character, Chinese fan translation by 2023 Team
+  Chinese2023Team,
+}
+
+impl TextEncoding {
+  fn description(self) -> &'static str {
+    match self {
+      Self::Japanese => "Japanese (half-width katakana)",
+      Self::Latin => "Latin",
+      Self::ChineseMobileTeam => "Chinese fan translation by Mobile Team",
+      Self::Chinese2023Team => "Chinese fan translation by 2023 Team",
+    }
+  }
+}
+
+impl NameType {
+  fn text_encoding(self) -> TextEncoding {
+    match self {
+      Self::Japanese | Self::KoreanFanTranslation => TextEncoding::Japanese,
+      Self::English | Self::German | Self::Spanish | Self::French | Self::Italian | Self::PolishFanTranslation => TextEncoding::Latin,
+      Self::ChineseFanTranslationMobileTeam => TextEncoding::ChineseMobileTeam,
+      Self::ChineseFanTranslation2023Team => TextEncoding::Chinese2023Team,
+    }
+  }
+}
+
+/// A target game version, it bundles the names, the build date and the text encoding of that version together.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy)]
+enum RegionProfile {
+  Japan,
+  USA,
+  Europe,
+  Germany,
+  Spain,
+  France,
+  Italy,
+  // TLA only
+  ChineseFanTranslationMobileTeamVersion,
+  // TBS only
+  ChineseFanTranslation2023TeamVersion,
+  // TBS only
+  PolishFanTranslationVersion,
+  KoreanFanTranslationVersion,
+}
+
+impl RegionProfile {
+  fn name_type(self) -> NameType {
+    match self {
+      Self::Japan => NameType::Japanese,
+      Self::USA | Self::Europe => NameType::English,
+      Self::Germany => NameType::German,
+      Self::Spain => NameType::Spanish,
+      Self::France => NameType::French,
+      Self::Italy => NameType::Italian,
+      Self::ChineseFanTranslationMobileTeamVersion => NameType::ChineseFanTranslationMobileTeam,
+      Self::ChineseFanTranslation2023TeamVersion => NameType::ChineseFanTranslation2023Team,
+      Self::PolishFanTranslationVersion => NameType::PolishFanTranslation,
+      Self::KoreanFanTranslationVersion => NameType::KoreanFanTranslation,
+    }
+  }
+
+  fn build_date_type(self) -> BuildDateType {
+    match self {
+      Self::Japan => BuildDateType::Japan,
+      Self::USA => BuildDateType::USA,
+      Self::Europe => BuildDateType::Europe,
+      Self::Germany => BuildDateType::Germany,
+      Self::Spain => BuildDateType::Spain,
+      Self::France => BuildDateType::France,
+      Self::Italy => BuildDateType::Italy,
+      Self::ChineseFanTranslationMobileTeamVersion => BuildDateType::ChineseFanTranslationMobileTeamVersion,
+      Self::ChineseFanTranslation2023TeamVersion => BuildDateType::ChineseFanTranslation2023TeamVersion,
+      Self::PolishFanTranslationVersion => BuildDateType::PolishFanTranslationVersion,
+      Self::KoreanFanTranslationVersion => BuildDateType::KoreanFanTranslationVersion,
+    }
+  }
+
+  fn text_encoding(self) -> TextEncoding {
+    self.name_type().text_encoding()
+  }
+
+  /// The fan translations only exist for one of the games, "None" if the version exists for both games.
+  fn game_type_only(self) -> Option<GameType> {
+    match self {
+      Self::ChineseFanTranslationMobileTeamVersion => Some(GameType::TheLostAge),
+      Self::ChineseFanTranslation2023TeamVersion | Self::PolishFanTranslationVersion => Some(GameType::TheBrokenSeal),
+      _ => None,
+    }
+  }
+
+  fn description(self) -> String {
+    self.to_possible_value().unwrap().get_help().unwrap().to_string()
+  }
+}
+
+impl ValueEnum for RegionProfile {
+  fn value_variants<'a>() -> &'a [Self] {
+    &[Self::Japan, Self::USA, Self::Europe, Self::Germany, Self::Spain, Self::France, Self::Italy,
+      Self::ChineseFanTranslationMobileTeamVersion, Self::ChineseFanTranslation2023TeamVersion, Self::PolishFanTranslationVersion, Self::KoreanFanTranslationVersion]
+  }
+
+  fn to_possible_value(&self) -> Option<PossibleValue> {
+    Some(match self {
+      Self::Japan => PossibleValue::new("j").help("Japan"),
+      Self::USA => PossibleValue::new("u").help("USA"),
+      Self::Europe => PossibleValue::new("e").help("Europe (English)"),
+      Self::Germany => PossibleValue::new("g").help("Germany"),
+      Self::Spain => PossibleValue::new("s").help("Spain"),
+      Self::France => PossibleValue::new("f").help("France"),
+      Self::Italy => PossibleValue::new("i").help("Italy"),
+      Self::ChineseFanTranslationMobileTeamVersion => PossibleValue::new("oc").help("Chinese fan translation by Mobile Team, TLA only"),
+      Self::ChineseFanTranslation2023TeamVersion => PossibleValue::new("nc").help("Chinese fan translation by 2023 Team, TBS only"),
+      Self::PolishFanTranslationVersion => PossibleValue::new("p").help("Polish fan translation, TBS only"),
+      Self::KoreanFanTranslationVersion => PossibleValue::new("k").help("Korean fan translation"),
+    })
+  }
+}
+
+/// Size of the output flash image.
+#[derive(Clone, Copy)]
+enum FlashSize {
+  Size64K,
+  Size128K,
+}
+
+impl FlashSize {
+  fn size(self) -> usize {
+    match self {
+      Self::Size64K => save::FLASH_BANK_SIZE,
+      Self::Size128K => save::FLASH_BANK_SIZE * 2,
+    }
+  }
+}
+
+impl ValueEnum for FlashSize {
+  fn value_variants<'a>() -> &'a [Self] {
+    &[Self::Size64K, Self::Size128K]
+  }
+
+  fn to_possible_value(&self) -> Option<PossibleValue> {
+    Some(match self {
+      Self::Size64K => PossibleValue::new("64k").help("64KB, most emulators and flashcarts"),
+      Self::Size128K => PossibleValue::new("128k").help("128KB, like BizHawk .SaveRAM files, the second bank is filled with 0xFF"),
+    })
+  }
+}
+
+/// Limit the conversion to some save data only, the others are left as they are.
+#[derive(Default)]
+struct SlotFilter {
+  // Only convert save data whose current build date matches this version.
+  source_build_date_type_option: Option<BuildDateType>,
+  // Only convert save data with this slot number (0, 1 or 2).
+  slot_number_option: Option<u8>,
+}
+
+impl SlotFilter {
+  /// The message when no save data used by game is converted, it only names the filters that are given.
+  fn get_no_match_message(&self) -> String {
+    let filter_names: Vec<&str> = [("\"--from\"", self.source_build_date_type_option.is_some()), ("\"--slot\"", self.slot_number_option.is_some())].into_iter()
+      .filter_map(|(filter_name, is_given)| is_given.then_some(filter_name))
+      .collect();
+    match filter_names.len() {
+      0 => String::from("There is no save data to convert!"),
+      1 => format!("There is no save data matching the given {} filter!", filter_names[0]),
+      _ => format!("There is no save data matching the given {} filters!", filter_names.join(" and ")),
+    }
+  }
+}
+
 fn main() {
   let matches = Command::new("Golden Sun Save Converter")
     .version(crate_version!())
@@ -202,106 +412,796 @@ fn main() {
       Note:\n\
       1. This tool also supports some other languages' fan translation version.\n\
       2. If the build date in the save file does not match the build date the game ROM,\n\
-         the game will force the player to start the game from the sanctum.")
+         the game will force the player to start the game from the sanctum.\n\
+      3. \"--to\" sets both names and build date of a game version at once,\n\
+         \"--name\" and \"--date\" can still be used to override one of them.")
     .args(&[
-      arg!(<INPUT_FILE> "Golden Sun/Golden Sun: The Lost Age save file").value_parser(value_parser!(PathBuf)).required(true),
+      arg!(<INPUT_FILE> "Golden Sun/Golden Sun: The Lost Age save file(s), directories or glob patterns").value_parser(value_parser!(PathBuf)).required(true).num_args(1..),
       // value_parser(clap::builder::PossibleValuesParser::new(["j", "e", "g", "s", "f", "i", "oc", "nc", "p", "k"]))
       arg!(-n --name <VALUE> "The version of the names of playable characters").value_parser(clap::builder::EnumValueParser::<NameType>::new()),
       // value_parser(clap::builder::PossibleValuesParser::new(["j", "u", "e", "g", "s", "f", "i", "oc", "nc", "p", "k"]))
       arg!(-d --date <VALUE> "Build date version").value_parser(clap::builder::EnumValueParser::<BuildDateType>::new()),
-      arg!(-o --output <OUTPUT_FILE> "Output save file location").value_parser(value_parser!(PathBuf))
+      arg!(-t --to <PROFILE> "Target game version, sets both names and build date, it must exist for the game of the input save file").value_parser(clap::builder::EnumValueParser::<RegionProfile>::new()),
+      arg!(-o --output <OUTPUT_FILE> "Output save file location, or output directory when converting more than one file").value_parser(value_parser!(PathBuf)),
+      arg!(--"raw-output" "Write a plain flash image, even if the input save file is in another format like No$GBA or byte-swapped"),
+      arg!(--wrap <FORMAT> "Write the converted save file in this format, for PC link software of cheat devices").value_parser(clap::builder::EnumValueParser::<WrapFormat>::new()).conflicts_with_all(["raw-output", "in-place"]),
+      arg!(--"wrap-like" <TEMPLATE> "Write the converted save data into a copy of this save file, in its format").value_parser(value_parser!(PathBuf)).conflicts_with_all(["raw-output", "in-place", "wrap"]),
+      arg!(--rom <ROM_FILE> "Game ROM, its header is used to create the header of \"--wrap\" save file formats").value_parser(value_parser!(PathBuf)).requires("wrap"),
+      arg!(--container <SIZE> "Pad or truncate the flash image to this size, default is the size of input").value_parser(clap::builder::EnumValueParser::<FlashSize>::new()),
+      arg!(--"ignore-trailing" "Accept save files with unknown data after the flash image, the data is written back unchanged"),
+      arg!(-r --recursive "Also convert save files in subdirectories of input directories"),
+      arg!(-i --"in-place" "Overwrite the input save file, the original one is kept as a timestamped backup").conflicts_with("output"),
+      arg!(--"keep-backups" <COUNT> "With \"--in-place\", how many backups of the save file are kept").value_parser(value_parser!(u16).range(1..)).default_value("3").requires("in-place"),
+      arg!(-f --from <VALUE> "Only convert save data whose current build date matches this version").value_parser(clap::builder::EnumValueParser::<BuildDateType>::new()),
+      arg!(-s --slot <SLOT> "Only convert save data in this slot (0, 1 or 2)").value_parser(value_parser!(u8).range(0..=i64::from(MAX_VALID_SLOT_NUMBER))),
+      arg!(-u --undo "Also write an undo file next to the output save file, it can be used by \"revert\" command"),
+      arg!(--"dry-run" "Show what will be changed without writing anything"),
+      arg!(--hex "With \"--dry-run\", also show a hex diff of every changed range").requires("dry-run")
     ])
     .group(ArgGroup::new("args")
-      .args(["name", "date"])
+      .args(["name", "date", "to"])
       .required(true)
       .multiple(true)
     )
+    .subcommand(Command::new("revert")
+      .about("Restore the original names and build dates of a converted save file by its undo file")
+      .args(&[
+        arg!(<INPUT_FILE> "Converted save file").value_parser(value_parser!(PathBuf)).required(true),
+        arg!(--"undo-file" <UNDO_FILE> "Undo file location, default is \"<INPUT_FILE>.undo\"").value_parser(value_parser!(PathBuf)),
+        arg!(-o --output <OUTPUT_FILE> "Output save file location").value_parser(value_parser!(PathBuf))
+      ])
+    )
+    .subcommand(Command::new("diff")
+      .about("Compare two save files slot by slot and field by field")
+      .args(&[
+        arg!(<FILE_A> "The first save file").value_parser(value_parser!(PathBuf)).required(true),
+        arg!(<FILE_B> "The second save file").value_parser(value_parser!(PathBuf)).required(true)
+      ])
+    )
+    .subcommand(Command::new("export")
+      .about("Export a save file to JSON")
+      .args(&[
+        arg!(<INPUT_FILE> "Golden Sun/Golden Sun: The Lost Age save file").value_parser(value_parser!(PathBuf)).required(true),
+        arg!(-o --output <OUTPUT_FILE> "Output JSON file location, default is \"<INPUT_FILE stem>.json\"").value_parser(value_parser!(PathBuf))
+      ])
+    )
+    .subcommand(Command::new("import")
+      .about("Rebuild a save file from exported JSON, edited fields are applied and checksums are recalculated")
+      .args(&[
+        arg!(<INPUT_FILE> "JSON file exported by this tool").value_parser(value_parser!(PathBuf)).required(true),
+        arg!(-o --output <OUTPUT_FILE> "Output save file location, default is \"<INPUT_FILE stem>_imported.sav\"").value_parser(value_parser!(PathBuf))
+      ])
+    )
+    .subcommand(Command::new("repack")
+      .about("Move save data to another save file format without converting it")
+      .args(&[
+        arg!(<INPUT_FILE> "Golden Sun/Golden Sun: The Lost Age save file").value_parser(value_parser!(PathBuf)).required(true),
+        arg!(-o --output <OUTPUT_FILE> "Output save file location, default is \"<INPUT_FILE stem>_repacked.<extension>\"").value_parser(value_parser!(PathBuf)),
+        arg!(--"raw-output" "Write a plain flash image"),
+        arg!(--"wrap-like" <TEMPLATE> "Write the save data into a copy of this save file, in its format").value_parser(value_parser!(PathBuf))
+      ])
+      .group(ArgGroup::new("format")
+        .args(["raw-output", "wrap-like"])
+        .required(true)
+      )
+    )
+    .subcommand(Command::new("info")
+      .about("Show the save data in each slot, like characters' levels and stats")
+      .args(&[
+        arg!(<INPUT_FILE> "Golden Sun/Golden Sun: The Lost Age save file").value_parser(value_parser!(PathBuf)).required(true),
+        arg!(-s --slot <SLOT> "Only show the save data in this slot (0, 1 or 2)").value_parser(value_parser!(u8).range(0..=i64::from(MAX_VALID_SLOT_NUMBER))),
+        arg!(--data <DATA_DIR> "Directory of game data files like \"items.txt\", used for item, psynergy and map names, none of them are built in").value_parser(value_parser!(PathBuf))
+      ])
+    )
+    .subcommand(Command::new("edit")
+      .about("Edit the save data in a slot, checksum is recalculated")
+      .args(&[
+        arg!(<INPUT_FILE> "Golden Sun/Golden Sun: The Lost Age save file").value_parser(value_parser!(PathBuf)).required(true),
+        arg!(--set <ASSIGNMENT> "Set a value, like \"felix.level=40\", can be used more than once, see \"info\" for all fields").required_unless_present("recompute").action(clap::ArgAction::Append),
+        arg!(-s --slot <SLOT> "The slot to edit (0, 1 or 2), can be omitted if there is only one slot").value_parser(value_parser!(u8).range(0..=i64::from(MAX_VALID_SLOT_NUMBER))),
+        arg!(-o --output <OUTPUT_FILE> "Output save file location, default is \"<INPUT_FILE stem>_edited.<extension>\"").value_parser(value_parser!(PathBuf)),
+        arg!(--data <DATA_DIR> "Directory of game data files like \"items.txt\", used for item names, named locations and checks, none of them are built in").value_parser(value_parser!(PathBuf)),
+        arg!(--recompute "After the changes, show classes, stats and elemental levels recalculated from base stats, djinn and equipment with an approximate formula, they are not written to the save file, needs data files"),
+        arg!(--"dry-run" "Show what will be changed without writing anything, like the classes characters would have after djinn changes")
+      ])
+    )
+    .subcommand(Command::new("flags")
+      .about("View and change event flags, the story progress like bosses beaten and chests opened, only party member flags are named without \"--data\"")
+      .subcommand_required(true)
+      .subcommand(Command::new("list")
+        .about("List the event flags that are set")
+        .args(&[
+          arg!(<INPUT_FILE> "Golden Sun/Golden Sun: The Lost Age save file").value_parser(value_parser!(PathBuf)).required(true),
+          arg!(-s --slot <SLOT> "The slot to show (0, 1 or 2), can be omitted if there is only one slot").value_parser(value_parser!(u8).range(0..=i64::from(MAX_VALID_SLOT_NUMBER))),
+          arg!(-a --all "Also list named flags that are not set"),
+          arg!(--data <DATA_DIR> "Directory of game data files, flag names in \"tbs_flags.txt\"/\"tla_flags.txt\" are added to the built-in ones").value_parser(value_parser!(PathBuf))
+        ])
+      )
+      .subcommand(Command::new("diff")
+        .about("Compare the event flags of two slots or two save files")
+        .args(&[
+          arg!(<FILE_A> "The first save file").value_parser(value_parser!(PathBuf)).required(true),
+          arg!([FILE_B] "The second save file, default is the first save file").value_parser(value_parser!(PathBuf)),
+          arg!(-s --slot <SLOT> "The slot in the first save file, can be omitted if there is only one slot").value_parser(value_parser!(u8).range(0..=i64::from(MAX_VALID_SLOT_NUMBER))),
+          arg!(--"other-slot" <SLOT> "The slot in the second save file, can be omitted if there is only one slot").value_parser(value_parser!(u8).range(0..=i64::from(MAX_VALID_SLOT_NUMBER))),
+          arg!(--data <DATA_DIR> "Directory of game data files, flag names in \"tbs_flags.txt\"/\"tla_flags.txt\" are added to the built-in ones").value_parser(value_parser!(PathBuf))
+        ])
+      )
+      .subcommand(Command::new("set")
+        .about("Set event flags, checksum is recalculated")
+        .args(get_flags_edit_args())
+      )
+      .subcommand(Command::new("clear")
+        .about("Clear event flags, checksum is recalculated")
+        .args(get_flags_edit_args())
+      )
+    )
+    .args_conflicts_with_subcommands(true)
+    .subcommand_negates_reqs(true)
     .get_matches();
 
-  let mut pc_name_type_option: Option<NameType> = None;
+  match matches.subcommand() {
+    Some(("revert", sub_matches)) => {
+      run_revert(sub_matches);
+      return;
+    }
+    Some(("diff", sub_matches)) => {
+      run_diff(sub_matches);
+      return;
+    }
+    Some(("export", sub_matches)) => {
+      run_export(sub_matches);
+      return;
+    }
+    Some(("import", sub_matches)) => {
+      run_import(sub_matches);
+      return;
+    }
+    Some(("repack", sub_matches)) => {
+      run_repack(sub_matches);
+      return;
+    }
+    Some(("info", sub_matches)) => {
+      run_info(sub_matches);
+      return;
+    }
+    Some(("edit", sub_matches)) => {
+      run_edit(sub_matches);
+      return;
+    }
+    Some(("flags", sub_matches)) => {
+      run_flags(sub_matches);
+      return;
+    }
+    _ => {}
+  }
+
+  let region_profile_option: Option<RegionProfile> = matches.get_one("to").copied();
+
+  // Explicit "--name" and "--date" override the ones from "--to".
+  let mut pc_name_type_option: Option<NameType> = region_profile_option.map(RegionProfile::name_type);
   if let Some(name_type) = matches.get_one("name") {
     pc_name_type_option = Some(*name_type);
   };
 
-  let mut build_date_type_option: Option<BuildDateType> = None;
+  let mut build_date_type_option: Option<BuildDateType> = region_profile_option.map(RegionProfile::build_date_type);
   if let Some(build_date_type) = matches.get_one("date") {
     build_date_type_option = Some(*build_date_type);
   }
 
+  // Names in another encoding will not be displayed correctly in the target game version.
+  if let (Some(region_profile), Some(pc_name_type)) = (region_profile_option, pc_name_type_option) {
+    if region_profile.text_encoding() != pc_name_type.text_encoding() {
+      println!("Warning: The target game version uses {} text encoding,", region_profile.text_encoding().description());
+      println!("but the names will be written in {} text encoding, they may not be displayed correctly.", pc_name_type.text_encoding().description());
+    }
+  }
+
+  let mut convert_options = ConvertOptions {
+    region_profile_option,
+    pc_name_type_option,
+    build_date_type_option,
+    slot_filter: SlotFilter {
+      source_build_date_type_option: matches.get_one("from").copied(),
+      slot_number_option: matches.get_one("slot").copied(),
+    },
+    write_undo: matches.get_flag("undo"),
+    dry_run: matches.get_flag("dry-run"),
+    show_hex_diff: matches.get_flag("hex"),
+    in_place_backup_count_option: if matches.get_flag("in-place") {
+      Some(usize::from(*matches.get_one::<u16>("keep-backups").unwrap()))
+    } else {
+      None
+    },
+    raw_output: matches.get_flag("raw-output"),
+    wrap_format_option: matches.get_one("wrap").copied(),
+    rom_header_option: None,
+    template_container_option: None,
+    flash_size_option: matches.get_one("container").copied(),
+    ignore_trailing: matches.get_flag("ignore-trailing"),
+  };
+
+  // Only the header of game ROM is needed.
+  if let Some(rom_path) = matches.get_one::<PathBuf>("rom") {
+    let mut rom_header = vec![0u8; container::ROM_HEADER_SIZE];
+    if fs::File::open(rom_path).and_then(|mut rom_file| rom_file.read_exact(&mut rom_header)).is_err() || !container::is_gba_rom_header(&rom_header) {
+      eprintln!("\"{}\" is not a valid GBA ROM!", rom_path.to_str().unwrap());
+      return;
+    }
+    convert_options.rom_header_option = Some(rom_header);
+  }
+
+  if let Some(template_path) = matches.get_one::<PathBuf>("wrap-like") {
+    match read_template_container(template_path) {
+      Ok(template_container) => convert_options.template_container_option = Some(template_container),
+      Err(error_message) => {
+        eprintln!("{error_message}");
+        return;
+      }
+    }
+  }
+
+  // More than one input, directories or glob patterns.
+  let raw_input_paths: Vec<&PathBuf> = matches.get_many::<PathBuf>("INPUT_FILE").unwrap().collect();
+  if raw_input_paths.len() > 1 || batch::is_batch_input(raw_input_paths[0]) {
+    batch::run_batch(&raw_input_paths, matches.get_one::<PathBuf>("output"), matches.get_flag("recursive"), &convert_options);
+    return;
+  }
+
+  let raw_input_path = raw_input_paths[0];
+  let output_path;
+  if convert_options.in_place_backup_count_option.is_some() {
+    output_path = PathBuf::from(raw_input_path);
+  } else if let Some(raw_output) = matches.get_one::<PathBuf>("output") {
+    output_path = PathBuf::from(raw_output);
+  } else {
+    output_path = get_default_output_path(raw_input_path, "_output", convert_options.wrap_format_option);
+  }
+
+  match convert_save_file(raw_input_path, &output_path, &convert_options) {
+    Ok(message) => print!("{message}"),
+    Err(ConvertError::Skipped(error_message) | ConvertError::Failed(error_message)) => eprintln!("{error_message}"),
+  }
+}
+
+/// Everything needed to convert a save file, shared by all input files.
+struct ConvertOptions {
+  // Checked against the game type of each save file, even when "--name" and "--date" override all of it.
+  region_profile_option: Option<RegionProfile>,
+  pc_name_type_option: Option<NameType>,
+  build_date_type_option: Option<BuildDateType>,
+  slot_filter: SlotFilter,
+  write_undo: bool,
+  dry_run: bool,
+  show_hex_diff: bool,
+  // Overwrite the input save file and keep this many backups.
+  in_place_backup_count_option: Option<usize>,
+  // Write a plain flash image instead of the input's container format.
+  raw_output: bool,
+  // Write the output save file in this format instead of the input's container format.
+  wrap_format_option: Option<WrapFormat>,
+  // The first 0xC0 bytes of game ROM, needed by "--wrap" when the input save file is in another format.
+  rom_header_option: Option<Vec<u8>>,
+  // Write the output save file in the format of "--wrap-like" template, with everything around the save data in it.
+  template_container_option: Option<SaveContainer>,
+  // Pad or truncate the flash image to this size.
+  flash_size_option: Option<FlashSize>,
+  // Accept unknown data after the flash image.
+  ignore_trailing: bool,
+}
+
+enum ConvertError {
+  // Not a Golden Sun save file, or there is nothing to convert in it.
+  Skipped(String),
+  Failed(String),
+}
+
+/// Read, convert and write one save file.
+/// Returns the messages for user, like dry run result or backup location.
+fn convert_save_file(input_path: &Path, output_path: &Path, convert_options: &ConvertOptions) -> Result<String, ConvertError> {
+  let mut message = String::new();
+
+  let LoadedSave { mut raw_save_file, mut save_container, game_type, loop_start_index } = read_save_file(input_path, convert_options.ignore_trailing)?;
+  if !matches!(save_container, SaveContainer::Raw) {
+    message.push_str(&format!("Save file format: {}\n", save_container.description()));
+  }
+  // The emulator or the software of the input save file can't read a plain flash image.
+  if convert_options.raw_output && convert_options.in_place_backup_count_option.is_some() && !matches!(save_container, SaveContainer::Raw) {
+    return Err(ConvertError::Failed(format!("\"--in-place\" can't replace the input save file ({}) with a plain flash image, please use \"--output\" with \"--raw-output\"!", save_container.description())));
+  }
+
+  // A save file already in the wanted format keeps its own header.
+  if let Some(wrap_format) = convert_options.wrap_format_option {
+    if !save_container.is_wrap_format(wrap_format) {
+      let Some(rom_header) = &convert_options.rom_header_option else {
+        return Err(ConvertError::Failed(String::from("\"--rom\" is needed to create the header of the output save file!")));
+      };
+      save_container = container::create_container(wrap_format, rom_header);
+    }
+  }
+
+  // The target game version must exist for the game of this save file.
+  if let Some(region_profile) = convert_options.region_profile_option {
+    if region_profile.game_type_only().is_some_and(|game_type_only| get_game_type_index(game_type_only) != get_game_type_index(game_type)) {
+      return Err(ConvertError::Failed(format!("The target game version doesn't match this save file, it's {}!", region_profile.description())));
+    }
+  }
+
+  // Simple validation for name type and game type combination.
+  if let Some(name_type) = convert_options.pc_name_type_option {
+    if matches!(name_type, NameType::ChineseFanTranslationMobileTeam) && matches!(game_type, GameType::TheBrokenSeal) {
+      return Err(ConvertError::Failed(String::from("All playable characters' names in Chinese fan translation by Mobile Team are TLA only!")));
+    } else if matches!(name_type, NameType::ChineseFanTranslation2023Team) && matches!(game_type, GameType::TheLostAge) {
+      return Err(ConvertError::Failed(String::from("All playable characters' names in Chinese fan translation by 2023 Team are TBS only!")));
+    } else if matches!(name_type, NameType::PolishFanTranslation) && matches!(game_type, GameType::TheLostAge) {
+      message.push_str("Although all playable characters' names in Polish fan translation are TBS only,\n");
+      message.push_str("but since these names are the same as those in the English version, so there will be no problem.\n");
+    }
+  }
+
+  // Only the first bank is converted, the second bank is kept as it is.
+  if save::has_blocks_in_second_bank(&raw_save_file) {
+    message.push_str("Warning: The second 64KB bank of this save file holds save data, which is not used by game and will not be converted.\n");
+  }
+  if let Some(flash_size) = convert_options.flash_size_option {
+    let output_container = convert_options.template_container_option.as_ref().unwrap_or(&save_container);
+    if !convert_options.raw_output && output_container.is_fixed_size() && raw_save_file.len() != flash_size.size() {
+      return Err(ConvertError::Failed(format!("The size of save data in {} can't be changed!", output_container.description())));
+    }
+    resize_flash_image(&mut raw_save_file, flash_size).map_err(ConvertError::Failed)?;
+  }
+
+  // Keep the original save data for undo file.
+  let raw_input_save = raw_save_file.clone();
+
+  // Convert save data.
+  let converted_count = convert_save(&mut raw_save_file, Some(game_type), loop_start_index, convert_options.pc_name_type_option, convert_options.build_date_type_option, &convert_options.slot_filter);
+  if converted_count == 0 {
+    return Err(ConvertError::Skipped(convert_options.slot_filter.get_no_match_message()));
+  }
+  // Only show the changes, nothing will be written.
+  if convert_options.dry_run {
+    message.push_str(&preview::get_dry_run_report(&raw_input_save, &raw_save_file, get_game_type_index(game_type), convert_options.show_hex_diff));
+    return Ok(message);
+  }
+
+  // Start to create and write output save file, in the same container format as input unless a raw image or a template is wanted.
+  let output_container = convert_options.template_container_option.as_ref().unwrap_or(&save_container);
+  let output_file_bytes = if convert_options.raw_output { raw_save_file.clone() } else { output_container.wrap(&raw_save_file) };
+  if let Some(backup_count) = convert_options.in_place_backup_count_option {
+    match backup::write_in_place(output_path, &output_file_bytes, backup_count) {
+      Ok(backup_path) => message.push_str(&format!("The original save file has been backed up to \"{}\".\n", backup_path.to_str().unwrap())),
+      Err(error) => return Err(ConvertError::Failed(format!("Failed to overwrite \"{}\": {error}", output_path.to_str().unwrap()))),
+    }
+  } else {
+    write_output_file(output_path, &output_file_bytes).map_err(ConvertError::Failed)?;
+  }
+
+  // Write undo file next to output save file.
+  if convert_options.write_undo {
+    let game_type_index = get_game_type_index(game_type);
+    let undo_path = get_undo_path(output_path);
+    fs::write(&undo_path, undo::create_undo_record(&raw_input_save, &raw_save_file, &output_file_bytes, game_type_index)).map_err(|_| ConvertError::Failed(format!("Failed to create \"{}\"!", undo_path.to_str().unwrap())))?;
+  }
+
+  Ok(message)
+}
+
+/// A save file read from disk, the flash image has been taken out of its container.
+struct LoadedSave {
+  raw_save_file: Vec<u8>,
+  save_container: SaveContainer,
+  game_type: GameType,
+  loop_start_index: usize,
+}
+
+/// Read a save file, get the flash image from its container, check its size and detect game type.
+fn read_save_file(input_path: &Path, ignore_trailing: bool) -> Result<LoadedSave, ConvertError> {
   // Read save file.
-  let raw_input_path = matches.get_one::<PathBuf>("INPUT_FILE").unwrap();
-  let mut input_file = File::open(raw_input_path).expect("An error occurred while opening save file!");
+  let Ok(file_bytes) = fs::read(input_path) else {
+    return Err(ConvertError::Failed(String::from("An error occurred while opening save file!")));
+  };
+  let (raw_save_file, save_container) = container::unwrap_save_file(file_bytes).map_err(ConvertError::Failed)?;
+  if let Some(trailing_size) = save_container.get_unknown_trailing_size_option() {
+    if !ignore_trailing {
+      return Err(ConvertError::Skipped(format!("The size of save file is not valid! There are {trailing_size} unknown bytes after the flash image, use \"--ignore-trailing\" to keep them as they are.")));
+    }
+  }
 
   /* Check the size of save file.
      The size of save file should be 64KB,
      though the .SaveRAM file created by Bizhawk is 128KB.
      Even its size is 128KB, seems it only use first 64KB space to store save data. */
-  let file_size = input_file.metadata().unwrap().len();
+  let file_size = raw_save_file.len();
   if file_size != 0x10000 && file_size != 0x20000 {
-    eprintln!("The size of save file is not valid!");
-    return;
+    return Err(ConvertError::Skipped(String::from("The size of save file is not valid!")));
   }
 
-  // Get raw save.
-  let mut raw_save_file = Vec::new();
-  input_file.read_to_end(&mut raw_save_file).unwrap();
-
   // Detect game/save type, also get loop start index.
   let (game_type_option, loop_start_index_option) = get_game_type_with_loop_start_index_option(&raw_save_file);
   if game_type_option.is_none() {
-    eprintln!("It's not a valid Golden Sun/Golden Sun: The Lost age save file! Or there is no save data in save file!");
+    return Err(ConvertError::Skipped(String::from("It's not a valid Golden Sun/Golden Sun: The Lost age save file! Or there is no save data in save file!")));
+  }
+
+  Ok(LoadedSave { raw_save_file, save_container, game_type: game_type_option.unwrap(), loop_start_index: loop_start_index_option.unwrap() })
+}
+
+/// Pad a 64KB flash image with an erased bank, or remove the second bank of a 128KB one if nothing is lost.
+fn resize_flash_image(raw_save_file: &mut Vec<u8>, flash_size: FlashSize) -> Result<(), String> {
+  if raw_save_file.len() > flash_size.size() {
+    if save::has_blocks_in_second_bank(raw_save_file) {
+      return Err(String::from("The second 64KB bank of this save file holds save data, it can't be truncated to 64KB!"));
+    }
+    if !save::is_second_bank_empty(raw_save_file) {
+      return Err(String::from("The second 64KB bank of this save file is not empty, it can't be truncated to 64KB!"));
+    }
+  }
+  raw_save_file.resize(flash_size.size(), 0xFF);
+
+  Ok(())
+}
+
+fn run_revert(matches: &ArgMatches) {
+  if let Err(error_message) = revert_save_file(matches) {
+    eprintln!("{error_message}");
+  }
+}
+
+fn revert_save_file(matches: &ArgMatches) -> Result<(), String> {
+  let raw_input_path = matches.get_one::<PathBuf>("INPUT_FILE").unwrap();
+  let file_bytes = fs::read(raw_input_path).map_err(|error| format!("Failed to open \"{}\": {error}", raw_input_path.to_str().unwrap()))?;
+  let (mut raw_save_file, save_container) = container::unwrap_save_file(file_bytes.clone())?;
+
+  let undo_path = match matches.get_one::<PathBuf>("undo-file") {
+    Some(raw_undo_path) => PathBuf::from(raw_undo_path),
+    None => get_undo_path(raw_input_path),
+  };
+  let undo_record = fs::read_to_string(&undo_path).map_err(|_| format!("Failed to read undo file \"{}\"!", undo_path.to_str().unwrap()))?;
+  undo::revert_save(&mut raw_save_file, &file_bytes, &undo_record)?;
+
+  let output_path = match matches.get_one::<PathBuf>("output") {
+    Some(raw_output) => PathBuf::from(raw_output),
+    None => get_default_output_path(raw_input_path, "_reverted", None),
+  };
+  write_output_file(&output_path, &save_container.wrap(&raw_save_file))
+}
+
+fn run_diff(matches: &ArgMatches) {
+  let mut raw_save_files = Vec::new();
+  for id in ["FILE_A", "FILE_B"] {
+    let raw_input_path = matches.get_one::<PathBuf>(id).unwrap();
+    match read_save_file(raw_input_path, false) {
+      Ok(loaded_save) => raw_save_files.push((loaded_save.raw_save_file, get_game_type_index(loaded_save.game_type))),
+      Err(ConvertError::Skipped(error_message) | ConvertError::Failed(error_message)) => {
+        eprintln!("\"{}\": {error_message}", raw_input_path.to_str().unwrap());
+        return;
+      }
+    }
+  }
+
+  print!("{}", diff::get_diff_report(&raw_save_files[0].0, raw_save_files[0].1, &raw_save_files[1].0, raw_save_files[1].1));
+}
+
+fn run_export(matches: &ArgMatches) {
+  let raw_input_path = matches.get_one::<PathBuf>("INPUT_FILE").unwrap();
+  let LoadedSave { raw_save_file, game_type, .. } = match read_save_file(raw_input_path, false) {
+    Ok(loaded_save) => loaded_save,
+    Err(ConvertError::Skipped(error_message) | ConvertError::Failed(error_message)) => {
+      eprintln!("{error_message}");
+      return;
+    }
+  };
+
+  let output_path = match matches.get_one::<PathBuf>("output") {
+    Some(raw_output) => PathBuf::from(raw_output),
+    None => raw_input_path.with_extension("json"),
+  };
+  let json = export::export_save(&raw_save_file, get_game_type_index(game_type));
+  if let Err(error_message) = write_output_file(&output_path, json.to_pretty_string().as_bytes()) {
+    eprintln!("{error_message}");
+  }
+}
+
+fn run_import(matches: &ArgMatches) {
+  let raw_input_path = matches.get_one::<PathBuf>("INPUT_FILE").unwrap();
+  let Ok(json_text) = fs::read_to_string(raw_input_path) else {
+    eprintln!("An error occurred while opening JSON file!");
     return;
+  };
+
+  let raw_save_file = match json::parse_json(&json_text).and_then(|json| export::import_save(&json)) {
+    Ok(raw_save_file) => raw_save_file,
+    Err(error_message) => {
+      eprintln!("{error_message}");
+      return;
+    }
+  };
+
+  let output_path = match matches.get_one::<PathBuf>("output") {
+    Some(raw_output) => PathBuf::from(raw_output),
+    None => get_default_output_path(&raw_input_path.with_extension("sav"), "_imported", None),
+  };
+  if let Err(error_message) = write_output_file(&output_path, &raw_save_file) {
+    eprintln!("{error_message}");
   }
-  let loop_start_index = loop_start_index_option.unwrap();
+}
 
-  // Simple validation for name type and game type combination.
-  if let Some(name_type) = pc_name_type_option {
-    let game_type = game_type_option.unwrap();
-    if matches!(name_type, NameType::ChineseFanTranslationMobileTeam) && matches!(game_type, GameType::TheBrokenSeal) {
-      eprintln!("All playable characters' names in Chinese fan translation by Mobile Team are TLA only!");
+fn run_repack(matches: &ArgMatches) {
+  let raw_input_path = matches.get_one::<PathBuf>("INPUT_FILE").unwrap();
+  let LoadedSave { raw_save_file, .. } = match read_save_file(raw_input_path, false) {
+    Ok(loaded_save) => loaded_save,
+    Err(ConvertError::Skipped(error_message) | ConvertError::Failed(error_message)) => {
+      eprintln!("{error_message}");
       return;
-    } else if matches!(name_type, NameType::ChineseFanTranslation2023Team) && matches!(game_type, GameType::TheLostAge) {
-      eprintln!("All playable characters' names in Chinese fan translation by 2023 Team are TBS only!");
+    }
+  };
+
+  let (output_file_bytes, default_output_path) = match matches.get_one::<PathBuf>("wrap-like") {
+    Some(template_path) => {
+      let template_container = match read_template_container(template_path) {
+        Ok(template_container) => template_container,
+        Err(error_message) => {
+          eprintln!("{error_message}");
+          return;
+        }
+      };
+      let mut default_output_path = get_default_output_path(raw_input_path, "_repacked", None);
+      if let Some(template_extension) = template_path.extension() {
+        default_output_path.set_extension(template_extension);
+      }
+      (template_container.wrap(&raw_save_file), default_output_path)
+    }
+    None => (raw_save_file, get_default_output_path(&raw_input_path.with_extension("sav"), "_repacked", None)),
+  };
+
+  let output_path = match matches.get_one::<PathBuf>("output") {
+    Some(raw_output) => PathBuf::from(raw_output),
+    None => default_output_path,
+  };
+  if let Err(error_message) = write_output_file(&output_path, &output_file_bytes) {
+    eprintln!("{error_message}");
+  }
+}
+
+fn run_info(matches: &ArgMatches) {
+  let raw_input_path = matches.get_one::<PathBuf>("INPUT_FILE").unwrap();
+  let LoadedSave { raw_save_file, game_type, .. } = match read_save_file(raw_input_path, false) {
+    Ok(loaded_save) => loaded_save,
+    Err(ConvertError::Skipped(error_message) | ConvertError::Failed(error_message)) => {
+      eprintln!("{error_message}");
+      return;
+    }
+  };
+
+  let game_data = match gamedata::GameData::load(matches.get_one::<PathBuf>("data").map(PathBuf::as_path)) {
+    Ok(game_data) => game_data,
+    Err(error_message) => {
+      eprintln!("{error_message}");
       return;
-    } else if matches!(name_type, NameType::PolishFanTranslation) && matches!(game_type, GameType::TheLostAge) {
-      println!("Although all playable characters' names in Polish fan translation are TBS only,");
-      println!("but since these names are the same as those in the English version, so there will be no problem.");
+    }
+  };
+
+  print!("{}", info::get_info_report(&raw_save_file, get_game_type_index(game_type), matches.get_one("slot").copied(), &game_data));
+}
+
+fn run_edit(matches: &ArgMatches) {
+  let raw_input_path = matches.get_one::<PathBuf>("INPUT_FILE").unwrap();
+  let LoadedSave { mut raw_save_file, save_container, game_type, .. } = match read_save_file(raw_input_path, false) {
+    Ok(loaded_save) => loaded_save,
+    Err(ConvertError::Skipped(error_message) | ConvertError::Failed(error_message)) => {
+      eprintln!("{error_message}");
+      return;
+    }
+  };
+
+  let game_data = match gamedata::GameData::load(matches.get_one::<PathBuf>("data").map(PathBuf::as_path)) {
+    Ok(game_data) => game_data,
+    Err(error_message) => {
+      eprintln!("{error_message}");
+      return;
+    }
+  };
+
+  let game_type_index = get_game_type_index(game_type);
+  let assignments: Vec<&String> = matches.get_many::<String>("set").map_or_else(Vec::new, Iterator::collect);
+  let block_range = match edit::get_slot_block_range(&raw_save_file, game_type_index, matches.get_one("slot").copied()) {
+    Ok(block_range) => block_range,
+    Err(error_message) => {
+      eprintln!("{error_message}");
+      return;
+    }
+  };
+  let raw_block = &mut raw_save_file[block_range];
+  let change_descriptions = match edit::apply_assignments(raw_block, &assignments, game_type_index, &game_data) {
+    Ok(change_descriptions) => change_descriptions,
+    Err(error_message) => {
+      eprintln!("{error_message}");
+      return;
+    }
+  };
+  for change_description in change_descriptions {
+    println!("{change_description}");
+  }
+  // Recomputed values are only shown, the formula is an approximation and is not checked against the game.
+  if matches.get_flag("recompute") {
+    println!("Recomputed values (approximate, not written to the save file):");
+    for recompute_description in stats::recompute_stats(raw_block, game_type_index, &game_data) {
+      println!("  {recompute_description}");
     }
   }
+  if matches.get_flag("dry-run") {
+    println!("Dry run, nothing is written.");
+    return;
+  }
+  if assignments.is_empty() {
+    println!("Nothing is changed, nothing is written.");
+    return;
+  }
 
-  // Convert save data.
-  convert_save(&mut raw_save_file, game_type_option, loop_start_index, pc_name_type_option, build_date_type_option);
-  // Start to create and write output save file.
-  let output_path;
-  let mut output_file;
-  if let Some(raw_output) = matches.get_one::<PathBuf>("output") {
-    output_path = PathBuf::from(raw_output);
-    let output_dir = output_path.parent().unwrap();
-    fs::create_dir_all(output_dir).expect("Failed to create directory!");
-  } else {
-    let path = Path::new(raw_input_path);
-    let parent = path.parent().unwrap().to_str().unwrap();
-    let file_stem = path.file_stem().unwrap().to_str().unwrap();
+  let output_path = match matches.get_one::<PathBuf>("output") {
+    Some(raw_output) => PathBuf::from(raw_output),
+    None => get_default_output_path(raw_input_path, "_edited", None),
+  };
+  if let Err(error_message) = write_output_file(&output_path, &save_container.wrap(&raw_save_file)) {
+    eprintln!("{error_message}");
+  }
+}
 
-    let file_extension = if path.extension().is_none() {
-      ""
-    } else {
-      path.extension().unwrap().to_str().unwrap()
-    };
+/// Arguments of "flags set" and "flags clear".
+fn get_flags_edit_args() -> [clap::Arg; 5] {
+  [
+    arg!(<INPUT_FILE> "Golden Sun/Golden Sun: The Lost Age save file").value_parser(value_parser!(PathBuf)).required(true),
+    arg!(<FLAG> "Flag indexes (like \"0x123\") or names, see \"flags list --all\"").required(true).num_args(1..),
+    arg!(-s --slot <SLOT> "The slot to edit (0, 1 or 2), can be omitted if there is only one slot").value_parser(value_parser!(u8).range(0..=i64::from(MAX_VALID_SLOT_NUMBER))),
+    arg!(-o --output <OUTPUT_FILE> "Output save file location, default is \"<INPUT_FILE stem>_edited.<extension>\"").value_parser(value_parser!(PathBuf)),
+    arg!(--data <DATA_DIR> "Directory of game data files, flag names in \"tbs_flags.txt\"/\"tla_flags.txt\" are added to the built-in ones").value_parser(value_parser!(PathBuf))
+  ]
+}
 
-    let mut file_name_str = String::new();
-    file_name_str.push_str(file_stem);
-    file_name_str.push_str("_output");
+fn run_flags(matches: &ArgMatches) {
+  let (flags_command, sub_matches) = matches.subcommand().unwrap();
+  let game_data = match gamedata::GameData::load(sub_matches.get_one::<PathBuf>("data").map(PathBuf::as_path)) {
+    Ok(game_data) => game_data,
+    Err(error_message) => {
+      eprintln!("{error_message}");
+      return;
+    }
+  };
+  let input_id = if flags_command == "diff" { "FILE_A" } else { "INPUT_FILE" };
+  let raw_input_path = sub_matches.get_one::<PathBuf>(input_id).unwrap();
+  let LoadedSave { mut raw_save_file, save_container, game_type, .. } = match read_save_file(raw_input_path, false) {
+    Ok(loaded_save) => loaded_save,
+    Err(ConvertError::Skipped(error_message) | ConvertError::Failed(error_message)) => {
+      eprintln!("\"{}\": {error_message}", raw_input_path.to_str().unwrap());
+      return;
+    }
+  };
+  let game_type_index = get_game_type_index(game_type);
+  let block_range = match edit::get_slot_block_range(&raw_save_file, game_type_index, sub_matches.get_one("slot").copied()) {
+    Ok(block_range) => block_range,
+    Err(error_message) => {
+      eprintln!("{error_message}");
+      return;
+    }
+  };
 
-    if !file_extension.is_empty() {
-      file_name_str.push('.');
-      file_name_str.push_str(file_extension);
+  match flags_command {
+    "list" => print!("{}", flags::get_flags_list_report(&raw_save_file[block_range], game_type_index, &game_data, sub_matches.get_flag("all"))),
+    "diff" => {
+      // Without the second save file, two slots of the same save file are compared.
+      let other_raw_save_file = match sub_matches.get_one::<PathBuf>("FILE_B") {
+        Some(other_raw_input_path) => match read_save_file(other_raw_input_path, false) {
+          Ok(loaded_save) if get_game_type_index(loaded_save.game_type) == game_type_index => loaded_save.raw_save_file,
+          Ok(_) => {
+            eprintln!("The two save files are not from the same game!");
+            return;
+          }
+          Err(ConvertError::Skipped(error_message) | ConvertError::Failed(error_message)) => {
+            eprintln!("\"{}\": {error_message}", other_raw_input_path.to_str().unwrap());
+            return;
+          }
+        },
+        None if sub_matches.contains_id("other-slot") => raw_save_file.clone(),
+        None => {
+          eprintln!("Please choose a second save file, or another slot with \"--other-slot\"!");
+          return;
+        }
+      };
+      let other_block_range = match edit::get_slot_block_range(&other_raw_save_file, game_type_index, sub_matches.get_one("other-slot").copied()) {
+        Ok(other_block_range) => other_block_range,
+        Err(error_message) => {
+          eprintln!("{error_message}");
+          return;
+        }
+      };
+      print!("{}", flags::get_flags_diff_report(&raw_save_file[block_range], &other_raw_save_file[other_block_range], game_type_index, &game_data));
     }
+    _ => {
+      let flag_names: Vec<&String> = sub_matches.get_many::<String>("FLAG").unwrap().collect();
+      let raw_block = &mut raw_save_file[block_range];
+      let change_descriptions = match flags::set_flags(raw_block, &flag_names, flags_command == "set", game_type_index, &game_data) {
+        Ok(change_descriptions) => change_descriptions,
+        Err(error_message) => {
+          eprintln!("{error_message}");
+          return;
+        }
+      };
+      save::update_checksum(raw_block, game_type_index);
+      for change_description in change_descriptions {
+        println!("{change_description}");
+      }
 
-    output_path = PathBuf::from(parent).join(file_name_str);
+      let output_path = match sub_matches.get_one::<PathBuf>("output") {
+        Some(raw_output) => PathBuf::from(raw_output),
+        None => get_default_output_path(raw_input_path, "_edited", None),
+      };
+      if let Err(error_message) = write_output_file(&output_path, &save_container.wrap(&raw_save_file)) {
+        eprintln!("{error_message}");
+      }
+    }
+  }
+}
+
+/// Read the container format of a template save file, the save data in it will be replaced.
+fn read_template_container(template_path: &Path) -> Result<SaveContainer, String> {
+  let Ok(file_bytes) = fs::read(template_path) else {
+    return Err(format!("An error occurred while opening template save file \"{}\"!", template_path.to_str().unwrap()));
+  };
+  let (raw_save_file, template_container) = container::unwrap_save_file(file_bytes)?;
+  if raw_save_file.len() != 0x10000 && raw_save_file.len() != 0x20000 {
+    return Err(format!("\"{}\" is not a valid template save file!", template_path.to_str().unwrap()));
+  }
+
+  Ok(template_container)
+}
+
+/// Create parent directories and write the file, used by subcommands.
+/// Write an output file, its directory is created if needed.
+fn write_output_file(output_path: &Path, bytes: &[u8]) -> Result<(), String> {
+  if let Some(output_dir) = output_path.parent() {
+    fs::create_dir_all(output_dir).map_err(|error| format!("Failed to create directory \"{}\": {error}", output_dir.to_str().unwrap()))?;
+  }
+  fs::write(output_path, bytes).map_err(|error| format!("Failed to create \"{}\": {error}", output_path.to_str().unwrap()))
+}
+
+/// Default output location: "<parent>/<file stem><suffix>.<extension>"
+/// The extension of "--wrap" format is used if there is one.
+fn get_default_output_path(input_path: &Path, suffix: &str, wrap_format_option: Option<WrapFormat>) -> PathBuf {
+  let parent = input_path.parent().unwrap().to_str().unwrap();
+  let file_stem = input_path.file_stem().unwrap().to_str().unwrap();
+
+  let file_extension = if let Some(wrap_format) = wrap_format_option {
+    wrap_format.file_extension()
+  } else if input_path.extension().is_none() {
+    ""
+  } else {
+    input_path.extension().unwrap().to_str().unwrap()
+  };
+
+  let mut file_name_str = String::new();
+  file_name_str.push_str(file_stem);
+  file_name_str.push_str(suffix);
+
+  if !file_extension.is_empty() {
+    file_name_str.push('.');
+    file_name_str.push_str(file_extension);
+  }
+
+  PathBuf::from(parent).join(file_name_str)
+}
+
+/// Undo file location: "<save file>.undo"
+fn get_undo_path(save_path: &Path) -> PathBuf {
+  let mut undo_path = save_path.as_os_str().to_owned();
+  undo_path.push(".undo");
+  PathBuf::from(undo_path)
+}
+
+fn get_game_type_index(game_type: GameType) -> usize {
+  match game_type {
+    GameType::TheBrokenSeal => 0,
+    GameType::TheLostAge => 1,
   }
-  output_file = File::create(output_path.clone()).unwrap_or_else(|_| panic!("Failed to create \"{}\"!", output_path.to_str().unwrap()));
-  output_file.write_all(&raw_save_file).unwrap_or_else(|_| panic!("Failed to create \"{}\"!", output_path.to_str().unwrap()));
 }
 
 fn get_game_type_with_loop_start_index_option(raw_save_file: &[u8]) -> (Option<GameType>, Option<usize>) {
@@ -354,11 +1254,12 @@ fn get_game_type_with_loop_start_index_option(raw_save_file: &[u8]) -> (Option<G
    Some sections have slot numbers of 3, 4, or 5,
    those sections are the second half of slots 0, 1, and 2 respectively.
    But seems the second half of the save doesn't store the data for generating password. */
-fn convert_save(raw_save_file: &mut [u8], game_type_option: Option<GameType>, loop_start_index: usize, pc_name_type_option: Option<NameType>, build_date_type_option: Option<BuildDateType>) {
-  let game_type_index = match game_type_option.unwrap() {
-    GameType::TheBrokenSeal => 0,
-    GameType::TheLostAge => 1,
-  };
+fn convert_save(raw_save_file: &mut [u8], game_type_option: Option<GameType>, loop_start_index: usize, pc_name_type_option: Option<NameType>, build_date_type_option: Option<BuildDateType>, slot_filter: &SlotFilter) -> usize {
+  let game_type_index = get_game_type_index(game_type_option.unwrap());
+
+  // Old blocks are converted too, but only the blocks used by game are counted.
+  let live_block_indices: Vec<usize> = save::get_live_blocks(raw_save_file, game_type_index).iter().map(|live_block| live_block.index).collect();
+  let mut converted_count = 0;
 
   for i in loop_start_index..MAX_LOOP_COUNT[game_type_index] {
     // Skip "invalid" save data.
@@ -371,6 +1272,21 @@ fn convert_save(raw_save_file: &mut [u8], game_type_option: Option<GameType>, lo
       continue;
     }
 
+    // Skip save data filtered out by user.
+    if let Some(slot_number) = slot_filter.slot_number_option {
+      if raw_save_file[i * SAVE_SLOT_SIZE[game_type_index] + HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX] != slot_number {
+        continue;
+      }
+    }
+    if let Some(source_build_date_type) = slot_filter.source_build_date_type_option {
+      if build_date_from_raw_save != GS_BUILD_DATE[game_type_index][get_build_date_type_index(source_build_date_type)] {
+        continue;
+      }
+    }
+    if live_block_indices.contains(&i) {
+      converted_count += 1;
+    }
+
     if let Some(pc_name_type) = pc_name_type_option {
       let pc_name_type_index = match pc_name_type {
         NameType::Japanese | NameType::KoreanFanTranslation => 0,
@@ -439,16 +1355,7 @@ fn convert_save(raw_save_file: &mut [u8], game_type_option: Option<GameType>, lo
        the game will force player to start from sanctum after loading save.
        Every language version has a different build date. */
     if let Some(build_date_type) = build_date_type_option {
-      let build_date_type_index = match build_date_type {
-        BuildDateType::Japan | BuildDateType::ChineseFanTranslation2023TeamVersion | BuildDateType::KoreanFanTranslationVersion => 0,
-        BuildDateType::USA | BuildDateType::Europe | BuildDateType::ChineseFanTranslationMobileTeamVersion | BuildDateType::PolishFanTranslationVersion => 1,
-        BuildDateType::Germany => 2,
-        BuildDateType::Spain => 3,
-        BuildDateType::France => 4,
-        BuildDateType::Italy => 5,
-      };
-
-      let build_date = GS_BUILD_DATE[game_type_index][build_date_type_index].to_le_bytes();
+      let build_date = GS_BUILD_DATE[game_type_index][get_build_date_type_index(build_date_type)].to_le_bytes();
       for j in 0..2 {
         raw_save_file[i * SAVE_SLOT_SIZE[game_type_index] + BUILD_DATE_LOCATION_INDEX[game_type_index][0][j]] = build_date[j];
         raw_save_file[i * SAVE_SLOT_SIZE[game_type_index] + BUILD_DATE_LOCATION_INDEX[game_type_index][1][j]] = build_date[j];
@@ -469,4 +1376,18 @@ fn convert_save(raw_save_file: &mut [u8], game_type_option: Option<GameType>, lo
       raw_save_file[i * SAVE_SLOT_SIZE[game_type_index] + HEADER_CHECKSUM_LOCATION_INDEX[j]] = checksum_bytes[j];
     }
   }
+
+  converted_count
+}
+
+/// Get the index of the build date version in `GS_BUILD_DATE`.
+fn get_build_date_type_index(build_date_type: BuildDateType) -> usize {
+  match build_date_type {
+    BuildDateType::Japan | BuildDateType::ChineseFanTranslation2023TeamVersion | BuildDateType::KoreanFanTranslationVersion => 0,
+    BuildDateType::USA | BuildDateType::Europe | BuildDateType::ChineseFanTranslationMobileTeamVersion | BuildDateType::PolishFanTranslationVersion => 1,
+    BuildDateType::Germany => 2,
+    BuildDateType::Spain => 3,
+    BuildDateType::France => 4,
+    BuildDateType::Italy => 5,
+  }
 }
@@ -0,0 +1,117 @@
+use crate::character::get_character_offset;
+use crate::edit::get_pc_index;
+use crate::field::{LEADER_NAME_LOCATION_INDEX, LEADER_NAME_SIZE, PC_LABEL, PC_NAME_SIZE};
+use crate::flags::EVENT_FLAGS_LOCATION_INDEX;
+use crate::PARTY_MEMBERS_COUNT;
+
+/* Party members, from the Golden Sun Hacking Community RAM map (block offset = RAM offset + 0x10).
+   0x50          Party members, event flags 0x000 - 0x007, one bit for each character (bit 0 is Isaac)
+   0x448/0x468   Party order (u8 character indexes, TBS/TLA), only the first entries for party members are used
+
+   The leader name at 0x10 is the name of the first party member, it's only shown in save select menu. */
+pub const PARTY_MEMBER_FLAGS_LOCATION_INDEX: usize = EVENT_FLAGS_LOCATION_INDEX;
+pub const PARTY_ORDER_LOCATION_INDEX: [usize; 2] = [0x448, 0x468];
+
+/* Story constraints.
+   Golden Sun: Isaac always leads, only Isaac, Garet, Ivan and Mia can join the party.
+   Golden Sun: The Lost Age: Felix or Jenna leads, Isaac, Garet, Ivan and Mia join the party together. */
+const PARTY_LEADER_PC_INDEXES: [&[usize]; 2] = [&[0], &[4, 5]];
+const PARTY_JOINABLE_PC_INDEXES: [&[usize]; 2] = [&[0, 1, 2, 3], &[0, 1, 2, 3, 4, 5, 6, 7]];
+const PARTY_TOGETHER_PC_INDEXES: [&[usize]; 2] = [&[], &[0, 1, 2, 3]];
+
+/// Party members in party order.
+pub fn get_party(raw_block: &[u8], game_type_index: usize) -> Vec<usize> {
+  let member_count = (raw_block[PARTY_MEMBER_FLAGS_LOCATION_INDEX] as usize & ((1 << PARTY_MEMBERS_COUNT[game_type_index]) - 1)).count_ones() as usize;
+  raw_block[PARTY_ORDER_LOCATION_INDEX[game_type_index]..PARTY_ORDER_LOCATION_INDEX[game_type_index] + member_count].iter().map(|pc_index| *pc_index as usize).collect()
+}
+
+pub fn describe_party(raw_block: &[u8], game_type_index: usize) -> String {
+  join_pc_labels(&get_party(raw_block, game_type_index), ", ")
+}
+
+/// Write party members and their order, and the leader name, after checking the story constraints.
+fn set_party(raw_block: &mut [u8], party: &[usize], game_type_index: usize) -> Result<(), String> {
+  validate_party(party, game_type_index)?;
+
+  let member_flags = party.iter().fold(raw_block[PARTY_MEMBER_FLAGS_LOCATION_INDEX] & !((1u16 << PARTY_MEMBERS_COUNT[game_type_index]) - 1) as u8, |member_flags, pc_index| member_flags | 1 << pc_index);
+  raw_block[PARTY_MEMBER_FLAGS_LOCATION_INDEX] = member_flags;
+  for (i, pc_index) in party.iter().enumerate() {
+    raw_block[PARTY_ORDER_LOCATION_INDEX[game_type_index] + i] = *pc_index as u8;
+  }
+
+  // The leader name has fewer bytes than character names, but names are never longer than it in official versions.
+  let leader_name_location_index = get_character_offset(party[0], game_type_index);
+  let mut leader_name = raw_block[leader_name_location_index..leader_name_location_index + PC_NAME_SIZE].to_vec();
+  leader_name.truncate(LEADER_NAME_SIZE);
+  raw_block[LEADER_NAME_LOCATION_INDEX..LEADER_NAME_LOCATION_INDEX + LEADER_NAME_SIZE].copy_from_slice(&leader_name);
+
+  Ok(())
+}
+
+pub fn validate_party(party: &[usize], game_type_index: usize) -> Result<(), String> {
+  let Some(leader_pc_index) = party.first() else {
+    return Err(String::from("The party can't be empty!"));
+  };
+  for (i, pc_index) in party.iter().enumerate() {
+    if !PARTY_JOINABLE_PC_INDEXES[game_type_index].contains(pc_index) {
+      return Err(format!("{} can't join the party in this game!", join_pc_labels(&[*pc_index], "")));
+    }
+    if party[..i].contains(pc_index) {
+      return Err(format!("{} is in the party more than once!", PC_LABEL[*pc_index]));
+    }
+  }
+  if !PARTY_LEADER_PC_INDEXES[game_type_index].contains(leader_pc_index) {
+    return Err(format!("{} can't lead the party, the leader must be {}!", PC_LABEL[*leader_pc_index], join_pc_labels(PARTY_LEADER_PC_INDEXES[game_type_index], " or ")));
+  }
+  let together_pc_indexes = PARTY_TOGETHER_PC_INDEXES[game_type_index];
+  let together_count = together_pc_indexes.iter().filter(|pc_index| party.contains(pc_index)).count();
+  if together_count != 0 && together_count != together_pc_indexes.len() {
+    return Err(format!("{} join the party together!", join_pc_labels(together_pc_indexes, ", ")));
+  }
+
+  Ok(())
+}
+
+/* "party=<character>, <character>, ..." sets party members and their order,
+   "party.add=<character>" adds a character to the end of the party,
+   "party.remove=<character>" removes a character from the party. */
+pub fn assign_party(raw_block: &mut [u8], action_option: Option<&str>, value: &str, game_type_index: usize) -> Result<String, String> {
+  let old_party = get_party(raw_block, game_type_index);
+  let mut new_party = old_party.clone();
+  match action_option {
+    None => new_party = value.split(',').map(|character_name| get_pc_index(character_name.trim(), game_type_index)).collect::<Result<Vec<usize>, String>>()?,
+    Some("add") => new_party.push(get_pc_index(value, game_type_index)?),
+    Some("remove") => {
+      let pc_index = get_pc_index(value, game_type_index)?;
+      let Some(i) = new_party.iter().position(|party_pc_index| *party_pc_index == pc_index) else {
+        return Err(format!("{} is not in the party!", PC_LABEL[pc_index]));
+      };
+      new_party.remove(i);
+    }
+    Some(action) => return Err(format!("Unknown party action \"{action}\", it should be \"add\" or \"remove\"!")),
+  }
+  set_party(raw_block, &new_party, game_type_index)?;
+
+  Ok(format!("Party: {} -> {}", join_pc_labels(&old_party, ", "), join_pc_labels(&new_party, ", ")))
+}
+
+/// The leader name should be the name of the first party member, an empty party is not checked.
+pub fn is_leader_name_consistent(raw_block: &[u8], game_type_index: usize) -> bool {
+  let Some(leader_pc_index) = get_party(raw_block, game_type_index).first().copied() else {
+    return true;
+  };
+  if leader_pc_index >= PARTY_MEMBERS_COUNT[game_type_index] {
+    return false;
+  }
+  let leader_name_location_index = get_character_offset(leader_pc_index, game_type_index);
+  let leader_name = &raw_block[leader_name_location_index..leader_name_location_index + LEADER_NAME_SIZE];
+
+  raw_block[LEADER_NAME_LOCATION_INDEX..LEADER_NAME_LOCATION_INDEX + LEADER_NAME_SIZE] == *leader_name
+}
+
+fn join_pc_labels(pc_indexes: &[usize], separator: &str) -> String {
+  if pc_indexes.is_empty() {
+    return String::from("none");
+  }
+  pc_indexes.iter().map(|pc_index| PC_LABEL.get(*pc_index).map_or_else(|| format!("[{pc_index:#04X}]"), |pc_label| (*pc_label).to_string())).collect::<Vec<String>>().join(separator)
+}
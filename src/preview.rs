@@ -0,0 +1,54 @@
+use std::fmt::Write;
+use crate::{HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX, MAX_LOOP_COUNT, SAVE_SLOT_SIZE};
+use crate::field::{describe_field_value, get_changed_ranges, get_save_fields};
+
+/// How many bytes are shown in each line of hex diff.
+const HEX_DIFF_LINE_SIZE: usize = 16;
+
+/// Get a report of what a conversion changed, for "--dry-run".
+pub fn get_dry_run_report(raw_input_save: &[u8], raw_output_save: &[u8], game_type_index: usize, show_hex_diff: bool) -> String {
+  let mut report = String::new();
+  let save_fields = get_save_fields(game_type_index);
+  let mut changed_block_count = 0;
+  for i in 0..MAX_LOOP_COUNT[game_type_index] {
+    let block_start = i * SAVE_SLOT_SIZE[game_type_index];
+    let block_end = block_start + SAVE_SLOT_SIZE[game_type_index];
+    let input_block = &raw_input_save[block_start..block_end];
+    let output_block = &raw_output_save[block_start..block_end];
+    if input_block == output_block {
+      continue;
+    }
+    changed_block_count += 1;
+
+    writeln!(report, "Block {i} (slot {}, file offset {block_start:#07X}):", input_block[HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX]).unwrap();
+    for save_field in &save_fields {
+      let field_range = save_field.offset..save_field.offset + save_field.size;
+      if input_block[field_range.clone()] == output_block[field_range.clone()] {
+        continue;
+      }
+      writeln!(report, "  {:<18}{} -> {}", format!("{}:", save_field.name), describe_field_value(save_field, &input_block[field_range.clone()], game_type_index), describe_field_value(save_field, &output_block[field_range], game_type_index)).unwrap();
+    }
+
+    if show_hex_diff {
+      writeln!(report, "  Hex diff (file offset / block offset):").unwrap();
+      for changed_range in get_changed_ranges(input_block, output_block) {
+        for line_start in changed_range.clone().step_by(HEX_DIFF_LINE_SIZE) {
+          let line_range = line_start..changed_range.end.min(line_start + HEX_DIFF_LINE_SIZE);
+          writeln!(report, "  {:#07X} / {:#06X}: {} -> {}", block_start + line_start, line_start, to_spaced_hex_string(&input_block[line_range.clone()]), to_spaced_hex_string(&output_block[line_range])).unwrap();
+        }
+      }
+    }
+  }
+
+  if changed_block_count == 0 {
+    writeln!(report, "Nothing will be changed.").unwrap();
+  } else {
+    writeln!(report, "{changed_block_count} block(s) will be changed, nothing has been written (dry run).").unwrap();
+  }
+
+  report
+}
+
+fn to_spaced_hex_string(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{byte:02X}")).collect::<Vec<String>>().join(" ")
+}
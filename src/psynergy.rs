@@ -0,0 +1,148 @@
+use std::fmt::Write;
+use crate::character::{find_character_field, get_character_offset, read_character_field};
+use crate::class::get_character_class_id;
+use crate::field::PC_LABEL;
+use crate::gamedata::{GameData, CLASS_DATA_FILE_NAME};
+use crate::inventory::get_character_item_ids;
+
+/* Psynergy in character data, 32 entries of u32 at 0x58, used entries are at the start of the list.
+   Bits 0 - 9    Psynergy ID, 0 is an empty entry
+   Other bits are kept as they are, their meaning is not known.
+
+   The game builds the list from the character's class and level, and from the items that give psynergy (like Lash Pebble).
+   A save file converted from another tool may have a wrong list, the game doesn't fix it until the class changes. */
+const PSYNERGY_OFFSET: usize = 0x58;
+const PSYNERGY_ENTRY_COUNT: usize = 32;
+const PSYNERGY_ID_MASK: u32 = 0x3FF;
+
+fn read_psynergy_entry(raw_block: &[u8], pc_index: usize, entry_index: usize, game_type_index: usize) -> u32 {
+  let location_index = get_character_offset(pc_index, game_type_index) + PSYNERGY_OFFSET + entry_index * 4;
+  u32::from_le_bytes(raw_block[location_index..location_index + 4].try_into().unwrap())
+}
+
+fn write_psynergy_entry(raw_block: &mut [u8], pc_index: usize, entry_index: usize, value: u32, game_type_index: usize) {
+  let location_index = get_character_offset(pc_index, game_type_index) + PSYNERGY_OFFSET + entry_index * 4;
+  raw_block[location_index..location_index + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// IDs of the psynergy a character has, in list order.
+pub fn get_character_psynergy_ids(raw_block: &[u8], pc_index: usize, game_type_index: usize) -> Vec<u16> {
+  (0..PSYNERGY_ENTRY_COUNT)
+    .map(|entry_index| (read_psynergy_entry(raw_block, pc_index, entry_index, game_type_index) & PSYNERGY_ID_MASK) as u16)
+    .take_while(|psynergy_id| *psynergy_id != 0)
+    .collect()
+}
+
+pub fn describe_character_psynergy(raw_block: &[u8], pc_index: usize, game_type_index: usize, game_data: &GameData) -> String {
+  let psynergy_names: Vec<String> = get_character_psynergy_ids(raw_block, pc_index, game_type_index).iter().map(|psynergy_id| game_data.get_psynergy_name(*psynergy_id)).collect();
+
+  if psynergy_names.is_empty() { String::from("none") } else { psynergy_names.join(", ") }
+}
+
+/* "<character>.psynergy.add=<psynergy>" adds psynergy to the end of the list,
+   "<character>.psynergy.remove=<psynergy>" removes it, the entries after it are moved forward. */
+pub fn assign_character_psynergy(raw_block: &mut [u8], pc_index: usize, action: &str, psynergy: &str, game_type_index: usize, game_data: &GameData) -> Result<String, String> {
+  let psynergy_id = game_data.find_psynergy_id(psynergy)?;
+  if psynergy_id == 0 || u32::from(psynergy_id) > PSYNERGY_ID_MASK {
+    return Err(format!("Psynergy ID must be between 1 and {PSYNERGY_ID_MASK:#05X}!"));
+  }
+  let psynergy_ids = get_character_psynergy_ids(raw_block, pc_index, game_type_index);
+  let psynergy_name = game_data.get_psynergy_name(psynergy_id);
+
+  let mut change_description = match action {
+    "add" => {
+      if psynergy_ids.contains(&psynergy_id) {
+        return Err(format!("{} already has {psynergy_name}!", PC_LABEL[pc_index]));
+      }
+      if psynergy_ids.len() == PSYNERGY_ENTRY_COUNT {
+        return Err(format!("{} can't have more than {PSYNERGY_ENTRY_COUNT} psynergy!", PC_LABEL[pc_index]));
+      }
+      write_psynergy_entry(raw_block, pc_index, psynergy_ids.len(), u32::from(psynergy_id), game_type_index);
+      format!("{}'s psynergy: added {psynergy_name}", PC_LABEL[pc_index])
+    }
+    "remove" => {
+      let Some(entry_index) = psynergy_ids.iter().position(|id| *id == psynergy_id) else {
+        return Err(format!("{} doesn't have {psynergy_name}!", PC_LABEL[pc_index]));
+      };
+      for next_entry_index in entry_index + 1..PSYNERGY_ENTRY_COUNT {
+        let value = read_psynergy_entry(raw_block, pc_index, next_entry_index, game_type_index);
+        write_psynergy_entry(raw_block, pc_index, next_entry_index - 1, value, game_type_index);
+      }
+      write_psynergy_entry(raw_block, pc_index, PSYNERGY_ENTRY_COUNT - 1, 0, game_type_index);
+      format!("{}'s psynergy: removed {psynergy_name}", PC_LABEL[pc_index])
+    }
+    _ => return Err(format!("Unknown psynergy action \"{action}\", it should be \"add\" or \"remove\"!")),
+  };
+
+  match check_character_psynergy(raw_block, pc_index, game_type_index, game_data) {
+    Ok(warnings) => {
+      for warning in warnings {
+        write!(change_description, "\n  Warning: {warning}").unwrap();
+      }
+    }
+    Err(error_message) => write!(change_description, "\n  Warning: {error_message}").unwrap(),
+  }
+
+  Ok(change_description)
+}
+
+/// Compare a character's psynergy with its class and items, needs class data in data directory.
+/// The error tells why the psynergy can't be checked, it should be shown, as no class data is built in.
+pub fn check_character_psynergy(raw_block: &[u8], pc_index: usize, game_type_index: usize, game_data: &GameData) -> Result<Vec<String>, String> {
+  if game_data.classes.is_empty() {
+    return Err(format!("Psynergy is not checked against classes, {CLASS_DATA_FILE_NAME} is not in data directory (see \"--data\")."));
+  }
+  let class_id = get_character_class_id(raw_block, pc_index, game_type_index);
+  let Some(class_data) = game_data.find_class(class_id) else {
+    return Err(format!("{}'s psynergy is not checked, class {class_id:#04X} is not in {CLASS_DATA_FILE_NAME}.", PC_LABEL[pc_index]));
+  };
+  let level = read_character_field(raw_block, pc_index, find_character_field("level").unwrap(), game_type_index) as u8;
+  let psynergy_ids = get_character_psynergy_ids(raw_block, pc_index, game_type_index);
+  let class_psynergy_ids: Vec<u16> = class_data.psynergy_levels.iter().filter(|(_, learn_level)| *learn_level <= level).map(|(psynergy_id, _)| *psynergy_id).collect();
+  let item_psynergy_ids: Vec<u16> = get_character_item_ids(raw_block, pc_index, game_type_index).iter()
+    .filter_map(|item_id| game_data.find_item(*item_id).and_then(|item_data| item_data.psynergy_id_option))
+    .collect();
+
+  let mut warnings = Vec::new();
+  for psynergy_id in psynergy_ids.iter().filter(|psynergy_id| !class_psynergy_ids.contains(psynergy_id) && !item_psynergy_ids.contains(psynergy_id)) {
+    warnings.push(format!("{} (level {level}, {}) has {}, but neither the class nor an item gives it.", PC_LABEL[pc_index], class_data.name, game_data.get_psynergy_name(*psynergy_id)));
+  }
+  for psynergy_id in class_psynergy_ids.iter().chain(&item_psynergy_ids).filter(|psynergy_id| !psynergy_ids.contains(psynergy_id)) {
+    warnings.push(format!("{} (level {level}, {}) should have {}.", PC_LABEL[pc_index], class_data.name, game_data.get_psynergy_name(*psynergy_id)));
+  }
+
+  Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::gamedata::ClassData;
+  use crate::SAVE_SLOT_SIZE;
+
+  #[test]
+  fn missing_class_data_is_reported() {
+    let raw_block = vec![0u8; SAVE_SLOT_SIZE[1]];
+    assert_eq!(check_character_psynergy(&raw_block, 0, 1, &GameData::default()), Err(String::from("Psynergy is not checked against classes, classes.txt is not in data directory (see \"--data\").")));
+
+    let game_data = GameData {
+      classes: vec![ClassData { id: 0x01, name: String::from("Knight"), psynergy_levels: Vec::new(), required_levels_option: None, base_element_indexes: Vec::new(), stat_multipliers_option: None }],
+      ..GameData::default()
+    };
+    assert_eq!(check_character_psynergy(&raw_block, 0, 1, &game_data), Err(String::from("Isaac's psynergy is not checked, class 0x00 is not in classes.txt.")));
+  }
+
+  #[test]
+  fn psynergy_is_checked_against_class() {
+    let mut raw_block = vec![0u8; SAVE_SLOT_SIZE[1]];
+    let game_data = GameData {
+      classes: vec![ClassData { id: 0x00, name: String::from("Squire"), psynergy_levels: vec![(0x001, 0)], required_levels_option: None, base_element_indexes: Vec::new(), stat_multipliers_option: None }],
+      ..GameData::default()
+    };
+    assert_eq!(check_character_psynergy(&raw_block, 0, 1, &game_data), Ok(vec![String::from("Isaac (level 0, Squire) should have psynergy 0x001.")]));
+
+    write_psynergy_entry(&mut raw_block, 0, 0, 0x002, 1);
+    let warnings = check_character_psynergy(&raw_block, 0, 1, &game_data).unwrap();
+    assert_eq!(warnings[0], "Isaac (level 0, Squire) has psynergy 0x002, but neither the class nor an item gives it.");
+  }
+}
@@ -0,0 +1,106 @@
+use crate::{CHECKSUM_RANGE, HEADER_CAMELOT_ASCII_STRING, HEADER_CHECKSUM_LOCATION_INDEX, HEADER_PRIORITY_LOCATION_INDEX, HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX, MAX_LOOP_COUNT, MAX_VALID_SLOT_NUMBER, SAVE_SLOT_SIZE};
+
+/// The slot numbers of the second half of TBS save data are 3, 4 and 5, 15 is the max slot number.
+const MAX_SLOT_NUMBER: u8 = 0x0F;
+
+/// The game only uses the first 64KB bank of flash, 128KB save files have a second bank.
+pub const FLASH_BANK_SIZE: usize = 0x10000;
+
+/// A block in save file with a valid "CAMELOT" header.
+pub struct SaveBlock {
+  // Block index in save file, not slot number.
+  pub index: usize,
+  pub slot_number: u8,
+  pub priority: u16,
+  pub is_checksum_valid: bool,
+}
+
+impl SaveBlock {
+  pub fn start(&self, game_type_index: usize) -> usize {
+    self.index * SAVE_SLOT_SIZE[game_type_index]
+  }
+}
+
+/// Calculate the checksum of a block, the 0x10 header is not included.
+/// See the comment in `convert_save` for more information.
+pub fn calculate_checksum(raw_block: &[u8], game_type_index: usize) -> u16 {
+  let mut checksum = 0u32;
+  for byte in &raw_block[0x10..0x10 + CHECKSUM_RANGE[game_type_index]] {
+    checksum += u32::from(*byte);
+  }
+
+  checksum as u16
+}
+
+/// Recalculate and write the checksum of a block after changing its data.
+pub fn update_checksum(raw_block: &mut [u8], game_type_index: usize) {
+  let checksum = calculate_checksum(raw_block, game_type_index);
+  raw_block[HEADER_CHECKSUM_LOCATION_INDEX[0]..=HEADER_CHECKSUM_LOCATION_INDEX[1]].copy_from_slice(&checksum.to_le_bytes());
+}
+
+/// Get all blocks with a valid header, including the old ones that are not used by game.
+pub fn get_save_blocks(raw_save_file: &[u8], game_type_index: usize) -> Vec<SaveBlock> {
+  let mut save_blocks = Vec::new();
+  for i in 0..MAX_LOOP_COUNT[game_type_index] {
+    let raw_block = &raw_save_file[i * SAVE_SLOT_SIZE[game_type_index]..(i + 1) * SAVE_SLOT_SIZE[game_type_index]];
+    if &raw_block[..HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX] != HEADER_CAMELOT_ASCII_STRING.as_bytes() || raw_block[HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX] > MAX_SLOT_NUMBER {
+      continue;
+    }
+
+    let checksum = u16::from_le_bytes([raw_block[HEADER_CHECKSUM_LOCATION_INDEX[0]], raw_block[HEADER_CHECKSUM_LOCATION_INDEX[1]]]);
+    save_blocks.push(SaveBlock {
+      index: i,
+      slot_number: raw_block[HEADER_SAVE_SLOT_NUMBER_LOCATION_INDEX],
+      priority: u16::from_le_bytes([raw_block[HEADER_PRIORITY_LOCATION_INDEX[0]], raw_block[HEADER_PRIORITY_LOCATION_INDEX[1]]]),
+      is_checksum_valid: checksum == calculate_checksum(raw_block, game_type_index),
+    });
+  }
+
+  save_blocks
+}
+
+/// Get the block used by game for each slot (0, 1 or 2), which is the one with the highest priority, sorted by slot number.
+/// The blocks with slot number 3, 4 and 5 in TBS are the second half of slots 0, 1 and 2, they are not included.
+pub fn get_live_blocks(raw_save_file: &[u8], game_type_index: usize) -> Vec<SaveBlock> {
+  get_highest_priority_blocks(raw_save_file, game_type_index).into_iter().filter(|live_block| live_block.slot_number <= MAX_VALID_SLOT_NUMBER).collect()
+}
+
+/// Get the block used by game for the second half of each TBS slot, sorted by slot number (3, 4 and 5).
+/// TLA doesn't split its save data, so there are none.
+pub fn get_live_second_half_blocks(raw_save_file: &[u8], game_type_index: usize) -> Vec<SaveBlock> {
+  if game_type_index != 0 {
+    return Vec::new();
+  }
+
+  get_highest_priority_blocks(raw_save_file, game_type_index).into_iter()
+    .filter(|live_block| live_block.slot_number > MAX_VALID_SLOT_NUMBER && live_block.slot_number <= MAX_VALID_SLOT_NUMBER * 2 + 1)
+    .collect()
+}
+
+fn get_highest_priority_blocks(raw_save_file: &[u8], game_type_index: usize) -> Vec<SaveBlock> {
+  let mut live_blocks: Vec<SaveBlock> = Vec::new();
+  for save_block in get_save_blocks(raw_save_file, game_type_index) {
+    match live_blocks.iter_mut().find(|live_block| live_block.slot_number == save_block.slot_number) {
+      Some(live_block) => {
+        if save_block.priority > live_block.priority {
+          *live_block = save_block;
+        }
+      }
+      None => live_blocks.push(save_block),
+    }
+  }
+  live_blocks.sort_by_key(|live_block| live_block.slot_number);
+
+  live_blocks
+}
+
+/// Whether the second bank of a 128KB save file holds blocks with "CAMELOT" header, they are never read by game.
+pub fn has_blocks_in_second_bank(raw_save_file: &[u8]) -> bool {
+  raw_save_file.len() > FLASH_BANK_SIZE
+    && raw_save_file[FLASH_BANK_SIZE..].chunks(SAVE_SLOT_SIZE[0]).any(|raw_block| raw_block.starts_with(HEADER_CAMELOT_ASCII_STRING.as_bytes()))
+}
+
+/// Whether the second bank of a 128KB save file is empty, only erased (0xFF) or zero bytes.
+pub fn is_second_bank_empty(raw_save_file: &[u8]) -> bool {
+  raw_save_file.get(FLASH_BANK_SIZE..).unwrap_or_default().iter().all(|byte| *byte == 0xFF || *byte == 0x00)
+}
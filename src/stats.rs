@@ -0,0 +1,151 @@
+use crate::character::{find_character_field, CharacterField, read_character_field, write_character_field};
+use crate::class::{compute_class_id, compute_elemental_levels, get_character_class_id, set_character_class_id};
+use crate::djinn::get_set_djinn;
+use crate::field::PC_LABEL;
+use crate::gamedata::{GameData, CLASS_DATA_FILE_NAME, DJINN_DATA_FILE_NAME, GROWTH_DATA_FILE_NAME, ITEM_DATA_FILE_NAME};
+use crate::inventory::get_equipped_item_ids;
+use crate::PARTY_MEMBERS_COUNT;
+
+/* The game calculates stats when level, class, djinn or equipment change, this is an approximation of it,
+   the formula is not taken from the game and is not checked against it, the results depend on the data files:
+   Base stats        from the character's growth at the level (growth.txt), kept as they are without growth data
+   Class             from base element and elemental levels (see class.rs), the stored class without class data
+   Stats             base stats * class multipliers / 100 + bonuses of set djinn + bonuses of equipped items
+   Elemental levels  base elemental levels + 1 for each set djinni
+   Current HP/PP are not more than max HP/PP.
+   No data file is built in, so every value that is kept because of missing data is noted.
+   Because the formula and the data are not verified, the results are only reported and never written to the save file. */
+const BASE_STAT_FIELD_KEYS: [&str; 6] = ["base_hp", "base_pp", "base_attack", "base_defense", "base_agility", "base_luck"];
+const STAT_FIELD_KEYS: [&str; 6] = ["max_hp", "max_pp", "attack", "defense", "agility", "luck"];
+const ELEMENTAL_LEVEL_FIELD_KEYS: [&str; 4] = ["venus_level", "mercury_level", "mars_level", "jupiter_level"];
+const CURRENT_STAT_FIELD_KEYS: [(&str, &str); 2] = [("hp", "max_hp"), ("pp", "max_pp")];
+
+/// Recalculate the stats of every character on a copy of the save block, returns a line for each value that would change,
+/// or why a character is skipped. The save block itself is not changed.
+pub fn recompute_stats(raw_block: &[u8], game_type_index: usize, game_data: &GameData) -> Vec<String> {
+  let mut scratch_raw_block = raw_block.to_vec();
+  let mut change_descriptions = Vec::new();
+  for (pc_index, pc_label) in PC_LABEL.iter().enumerate().take(PARTY_MEMBERS_COUNT[game_type_index]) {
+    match recompute_character_stats(&mut scratch_raw_block, pc_index, game_type_index, game_data) {
+      Ok(character_change_descriptions) => change_descriptions.extend(character_change_descriptions),
+      Err(error_message) => change_descriptions.push(format!("{pc_label}'s stats are not recalculated: {error_message}")),
+    }
+  }
+
+  change_descriptions
+}
+
+fn recompute_character_stats(raw_block: &mut [u8], pc_index: usize, game_type_index: usize, game_data: &GameData) -> Result<Vec<String>, String> {
+  // Check all data first, so nothing is written if something is missing.
+  let level = read_character_field(raw_block, pc_index, find_character_field("level").unwrap(), game_type_index) as u8;
+  let base_stats_option = game_data.find_growth(pc_index, level).map(|growth_data| growth_data.base_stats);
+  let (class_id, class_note_option) = match compute_class_id(raw_block, pc_index, game_type_index, game_data) {
+    Ok(class_id) => (class_id, None),
+    Err(error_message) => (get_character_class_id(raw_block, pc_index, game_type_index), Some(error_message)),
+  };
+  let Some(stat_multipliers) = game_data.find_class(class_id).and_then(|class_data| class_data.stat_multipliers_option) else {
+    return Err(format!("stat multipliers of {} are not in {CLASS_DATA_FILE_NAME} (see \"--data\")", game_data.get_class_name(class_id)));
+  };
+  let mut stat_bonuses = [0i32; 6];
+  for djinni in get_set_djinn(raw_block, pc_index, game_type_index) {
+    let Some(djinni_data) = game_data.find_djinni(djinni.name()) else {
+      return Err(format!("stat bonuses of {} are not in {DJINN_DATA_FILE_NAME}", djinni.name()));
+    };
+    stat_bonuses.iter_mut().zip(djinni_data.stat_bonuses).for_each(|(stat_bonus, djinni_stat_bonus)| *stat_bonus += djinni_stat_bonus);
+  }
+  for item_id in get_equipped_item_ids(raw_block, pc_index, game_type_index) {
+    let Some(item_stat_bonuses) = game_data.find_item(item_id).and_then(|item_data| item_data.stat_bonuses_option) else {
+      return Err(format!("stat bonuses of {} are not in {ITEM_DATA_FILE_NAME}", game_data.get_item_name(item_id)));
+    };
+    stat_bonuses.iter_mut().zip(item_stat_bonuses).for_each(|(stat_bonus, item_stat_bonus)| *stat_bonus += item_stat_bonus);
+  }
+
+  let mut change_descriptions = Vec::new();
+  if let Some(class_note) = class_note_option {
+    change_descriptions.push(format!("Note: {class_note} The stored class is used."));
+  }
+  if base_stats_option.is_none() {
+    change_descriptions.push(format!("Note: {}'s level {level} is not in {GROWTH_DATA_FILE_NAME}, the stored base stats are used.", PC_LABEL[pc_index]));
+  }
+  let old_class_id = get_character_class_id(raw_block, pc_index, game_type_index);
+  if old_class_id != class_id {
+    set_character_class_id(raw_block, pc_index, class_id, game_type_index);
+    change_descriptions.push(format!("{}'s class: {} -> {}", PC_LABEL[pc_index], game_data.get_class_name(old_class_id), game_data.get_class_name(class_id)));
+  }
+
+  let mut new_values: Vec<(&str, u32)> = Vec::new();
+  for (i, base_stat_field_key) in BASE_STAT_FIELD_KEYS.iter().enumerate() {
+    let base_stat_field = find_character_field(base_stat_field_key).unwrap();
+    let base_stat = match base_stats_option {
+      Some(base_stats) => clamp_stat(i64::from(base_stats[i]), base_stat_field),
+      None => read_character_field(raw_block, pc_index, base_stat_field, game_type_index),
+    };
+    let stat_field = find_character_field(STAT_FIELD_KEYS[i]).unwrap();
+    let stat = clamp_stat(i64::from(base_stat) * i64::from(stat_multipliers[i]) / 100 + i64::from(stat_bonuses[i]), stat_field);
+    new_values.push((base_stat_field_key, base_stat));
+    new_values.push((STAT_FIELD_KEYS[i], stat));
+  }
+  for (element_index, elemental_level) in compute_elemental_levels(raw_block, pc_index, game_type_index).iter().enumerate() {
+    let elemental_level_field = find_character_field(ELEMENTAL_LEVEL_FIELD_KEYS[element_index]).unwrap();
+    new_values.push((ELEMENTAL_LEVEL_FIELD_KEYS[element_index], (*elemental_level).min(elemental_level_field.max_value)));
+  }
+  // Current HP/PP are lowered first, they can't be more than max HP/PP.
+  for (current_key, max_key) in CURRENT_STAT_FIELD_KEYS {
+    let max_value = new_values.iter().find(|(key, _)| *key == max_key).unwrap().1;
+    let current_value = read_character_field(raw_block, pc_index, find_character_field(current_key).unwrap(), game_type_index);
+    new_values.insert(0, (current_key, current_value.min(max_value)));
+  }
+
+  for (key, new_value) in new_values {
+    let character_field = find_character_field(key).unwrap();
+    let old_value = read_character_field(raw_block, pc_index, character_field, game_type_index);
+    if old_value != new_value {
+      write_character_field(raw_block, pc_index, character_field, new_value, game_type_index)?;
+      change_descriptions.push(format!("{}'s {}: {old_value} -> {new_value}", PC_LABEL[pc_index], character_field.label));
+    }
+  }
+
+  Ok(change_descriptions)
+}
+
+/// Keep a value in the limits of the game.
+fn clamp_stat(value: i64, character_field: &CharacterField) -> u32 {
+  value.clamp(i64::from(character_field.min_value), i64::from(character_field.max_value)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::gamedata::{ClassData, GrowthData};
+  use crate::SAVE_SLOT_SIZE;
+
+  #[test]
+  fn missing_data_is_reported() {
+    let mut raw_block = vec![0u8; SAVE_SLOT_SIZE[0]];
+    assert_eq!(recompute_character_stats(&mut raw_block, 0, 0, &GameData::default()), Err(String::from("stat multipliers of class 0x00 are not in classes.txt (see \"--data\")")));
+
+    let game_data = GameData {
+      classes: vec![ClassData { id: 0x00, name: String::from("Squire"), psynergy_levels: Vec::new(), required_levels_option: None, base_element_indexes: Vec::new(), stat_multipliers_option: Some([100; 6]) }],
+      ..GameData::default()
+    };
+    let change_descriptions = recompute_character_stats(&mut raw_block, 0, 0, &game_data).unwrap();
+    assert_eq!(change_descriptions[0], "Note: Isaac's class is not computed, no class in classes.txt is for Venus adepts with elemental levels 0/0/0/0. The stored class is used.");
+    assert_eq!(change_descriptions[1], "Note: Isaac's level 0 is not in growth.txt, the stored base stats are used.");
+  }
+
+  #[test]
+  fn save_block_is_not_changed() {
+    let mut raw_block = vec![0u8; SAVE_SLOT_SIZE[0]];
+    let level_field = find_character_field("level").unwrap();
+    write_character_field(&mut raw_block, 0, level_field, 1, 0).unwrap();
+    let game_data = GameData {
+      classes: vec![ClassData { id: 0x00, name: String::from("Squire"), psynergy_levels: Vec::new(), required_levels_option: None, base_element_indexes: Vec::new(), stat_multipliers_option: Some([100; 6]) }],
+      growth: vec![GrowthData { pc_index: 0, level: 1, base_stats: [30, 20, 10, 10, 10, 5] }],
+      ..GameData::default()
+    };
+    let original_raw_block = raw_block.clone();
+    let change_descriptions = recompute_stats(&raw_block, 0, &game_data);
+    assert!(change_descriptions.contains(&String::from("Isaac's Max HP: 0 -> 30")));
+    assert_eq!(raw_block, original_raw_block);
+  }
+}
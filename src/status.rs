@@ -0,0 +1,80 @@
+use crate::edit::parse_number;
+
+/* Party status shown in save select menu, from the Golden Sun Hacking Community RAM map (block offset = RAM offset + 0x10).
+   0x254  Play time (u32, frames, 60 frames per second)
+   0x260  Coins (u32) */
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StatusKind {
+  Number,
+  // Frames, shown as hh:mm:ss
+  PlayTime,
+}
+
+pub struct StatusField {
+  // Used in "edit --set <key>=<value>".
+  pub key: &'static str,
+  pub label: &'static str,
+  pub kind: StatusKind,
+  // TBS, TLA
+  pub offset: [usize; 2],
+  pub size: usize,
+  // The limit of the game, the counters stop at it.
+  pub max_value: u32,
+}
+
+const FRAMES_PER_SECOND: u32 = 60;
+
+pub const STATUS_FIELDS: [StatusField; 2] = [
+  StatusField { key: "coins", label: "Coins", kind: StatusKind::Number, offset: [0x260, 0x260], size: 4, max_value: 999_999 },
+  // 99:59:59
+  StatusField { key: "play_time", label: "Play time", kind: StatusKind::PlayTime, offset: [0x254, 0x254], size: 4, max_value: (99 * 3600 + 59 * 60 + 59) * FRAMES_PER_SECOND },
+];
+
+pub fn find_status_field(key: &str) -> Option<&'static StatusField> {
+  STATUS_FIELDS.iter().find(|status_field| status_field.key.eq_ignore_ascii_case(key))
+}
+
+pub fn read_status_field(raw_block: &[u8], status_field: &StatusField, game_type_index: usize) -> u32 {
+  let location_index = status_field.offset[game_type_index];
+  raw_block[location_index..location_index + status_field.size].iter().rev().fold(0u32, |value, byte| (value << 8) | u32::from(*byte))
+}
+
+pub fn describe_status_value(status_field: &StatusField, value: u32) -> String {
+  match status_field.kind {
+    StatusKind::Number => value.to_string(),
+    StatusKind::PlayTime => {
+      let seconds = value / FRAMES_PER_SECOND;
+      format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    }
+  }
+}
+
+/// "<key>=<value>", play time is "hh:mm:ss" or a number of frames.
+pub fn assign_status_field(raw_block: &mut [u8], status_field: &StatusField, value: &str, game_type_index: usize) -> Result<String, String> {
+  let new_value = match status_field.kind {
+    StatusKind::PlayTime if value.contains(':') => parse_play_time(value)?,
+    _ => parse_number(value)?,
+  };
+  if new_value > status_field.max_value {
+    return Err(format!("{} can't be more than {}!", status_field.label, describe_status_value(status_field, status_field.max_value)));
+  }
+
+  let old_value = read_status_field(raw_block, status_field, game_type_index);
+  let location_index = status_field.offset[game_type_index];
+  raw_block[location_index..location_index + status_field.size].copy_from_slice(&new_value.to_le_bytes()[..status_field.size]);
+
+  Ok(format!("{}: {} -> {}", status_field.label, describe_status_value(status_field, old_value), describe_status_value(status_field, new_value)))
+}
+
+fn parse_play_time(value: &str) -> Result<u32, String> {
+  let error_message = || format!("Invalid play time \"{value}\", it should be like \"12:34:56\"!");
+  let parts: Vec<u32> = value.split(':').map(|part| part.trim().parse::<u32>().map_err(|_| error_message())).collect::<Result<Vec<u32>, String>>()?;
+  let [hours, minutes, seconds] = parts[..] else {
+    return Err(error_message());
+  };
+  if minutes >= 60 || seconds >= 60 {
+    return Err(error_message());
+  }
+
+  hours.checked_mul(3600).and_then(|hour_seconds| (hour_seconds + minutes * 60 + seconds).checked_mul(FRAMES_PER_SECOND)).ok_or_else(error_message)
+}
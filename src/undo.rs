@@ -0,0 +1,182 @@
+use std::fmt::Write;
+use crate::{MAX_LOOP_COUNT, SAVE_SLOT_SIZE};
+use crate::hash::crc32;
+use crate::field::{from_hex_string, get_changed_ranges, get_save_fields, to_hex_string};
+
+/// The first line of every undo file.
+const UNDO_FILE_MAGIC: &str = "Golden Sun Save Converter undo file";
+const UNDO_FILE_VERSION: u32 = 2;
+// Version 1 undo files don't have "output_file_crc32", they can still be used.
+const MIN_UNDO_FILE_VERSION: u32 = 1;
+
+/* Undo file is a plain text file:
+
+   Golden Sun Save Converter undo file
+   version 1
+   size 0x10000
+   input_crc32 0x01234567
+   output_crc32 0x89ABCDEF
+   output_file_crc32 0x76543210
+   block 0 0x0000
+   0x0008 7A1B Checksum
+   0x0010 4973616163000000 Leader name
+
+   The CRC32 values of input and output are of the flash images, "output_file_crc32" is of the whole output file on disk,
+   so a change in the container (like a footer or a PNG chunk) is found too.
+   Every field line stores the offset relative to its block, the original bytes and the field name.
+   Bytes changed outside known fields are stored with the name "Unknown". */
+pub fn create_undo_record(raw_input_save: &[u8], raw_output_save: &[u8], output_file_bytes: &[u8], game_type_index: usize) -> String {
+  let mut undo_record = String::new();
+  writeln!(undo_record, "{UNDO_FILE_MAGIC}").unwrap();
+  writeln!(undo_record, "version {UNDO_FILE_VERSION}").unwrap();
+  writeln!(undo_record, "size {:#X}", raw_input_save.len()).unwrap();
+  writeln!(undo_record, "input_crc32 {:#010X}", crc32(raw_input_save)).unwrap();
+  writeln!(undo_record, "output_crc32 {:#010X}", crc32(raw_output_save)).unwrap();
+  writeln!(undo_record, "output_file_crc32 {:#010X}", crc32(output_file_bytes)).unwrap();
+
+  let save_fields = get_save_fields(game_type_index);
+  for i in 0..MAX_LOOP_COUNT[game_type_index] {
+    let block_start = i * SAVE_SLOT_SIZE[game_type_index];
+    let block_end = block_start + SAVE_SLOT_SIZE[game_type_index];
+    let input_block = &raw_input_save[block_start..block_end];
+    let output_block = &raw_output_save[block_start..block_end];
+    if input_block == output_block {
+      continue;
+    }
+
+    writeln!(undo_record, "block {i} {block_start:#06X}").unwrap();
+    let mut recorded = vec![false; input_block.len()];
+    for save_field in &save_fields {
+      let field_range = save_field.offset..save_field.offset + save_field.size;
+      if input_block[field_range.clone()] == output_block[field_range.clone()] {
+        continue;
+      }
+      writeln!(undo_record, "{:#06X} {} {}", save_field.offset, to_hex_string(&input_block[field_range.clone()]), save_field.name).unwrap();
+      recorded[field_range].fill(true);
+    }
+
+    // Normally this tool only changes known fields, but just in case.
+    for changed_range in get_changed_ranges(input_block, output_block) {
+      for j in changed_range {
+        if !recorded[j] {
+          writeln!(undo_record, "{j:#06X} {:02X} Unknown", input_block[j]).unwrap();
+        }
+      }
+    }
+  }
+
+  undo_record
+}
+
+/// Restore original bytes from an undo record.
+/// The save file must be exactly the output file the undo record was created for, both its flash image and the file on disk.
+pub fn revert_save(raw_save_file: &mut [u8], file_bytes: &[u8], undo_record: &str) -> Result<(), String> {
+  let mut lines = undo_record.lines();
+  if lines.next() != Some(UNDO_FILE_MAGIC) {
+    return Err(String::from("It's not a valid undo file!"));
+  }
+
+  let mut version_option = None;
+  let mut size_option = None;
+  let mut input_crc32_option = None;
+  let mut output_crc32_option = None;
+  let mut output_file_crc32_option = None;
+  let mut block_start_option: Option<usize> = None;
+  let mut restored_fields = Vec::new();
+  for line in lines {
+    let mut tokens = line.splitn(3, ' ');
+    let (Some(key), Some(value)) = (tokens.next(), tokens.next()) else {
+      continue;
+    };
+    match key {
+      "version" => {
+        match value.parse::<u32>() {
+          Ok(version) if (MIN_UNDO_FILE_VERSION..=UNDO_FILE_VERSION).contains(&version) => version_option = Some(version),
+          _ => return Err(format!("Unsupported undo file version \"{value}\"!")),
+        }
+      }
+      "size" => size_option = Some(parse_hex(value)?),
+      "input_crc32" => input_crc32_option = Some(parse_hex(value)? as u32),
+      "output_crc32" => output_crc32_option = Some(parse_hex(value)? as u32),
+      "output_file_crc32" => output_file_crc32_option = Some(parse_hex(value)? as u32),
+      "block" => {
+        let Some(block_start) = tokens.next() else {
+          return Err(format!("Invalid line in undo file: \"{line}\""));
+        };
+        block_start_option = Some(parse_hex(block_start)?);
+      }
+      _ => {
+        let Some(block_start) = block_start_option else {
+          return Err(format!("Invalid line in undo file: \"{line}\""));
+        };
+        restored_fields.push((block_start + parse_hex(key)?, from_hex_string(value)?));
+      }
+    }
+  }
+
+  let (Some(version), Some(size), Some(input_crc32), Some(output_crc32)) = (version_option, size_option, input_crc32_option, output_crc32_option) else {
+    return Err(String::from("The undo file is incomplete!"));
+  };
+  if version >= 2 && output_file_crc32_option.is_none() {
+    return Err(String::from("The undo file is incomplete!"));
+  }
+  if raw_save_file.len() != size || crc32(raw_save_file) != output_crc32 || output_file_crc32_option.is_some_and(|output_file_crc32| crc32(file_bytes) != output_file_crc32) {
+    return Err(String::from("The undo file does not belong to this save file, or the save file has been changed after conversion!"));
+  }
+
+  for (location_index, original_bytes) in restored_fields {
+    if location_index + original_bytes.len() > raw_save_file.len() {
+      return Err(format!("Offset {location_index:#X} in undo file is out of range!"));
+    }
+    raw_save_file[location_index..location_index + original_bytes.len()].copy_from_slice(&original_bytes);
+  }
+
+  if crc32(raw_save_file) != input_crc32 {
+    return Err(String::from("The reverted save file does not match the original one!"));
+  }
+
+  Ok(())
+}
+
+fn parse_hex(value: &str) -> Result<usize, String> {
+  usize::from_str_radix(value.trim_start_matches("0x").trim_start_matches("0X"), 16).map_err(|_| format!("Invalid number \"{value}\" in undo file!"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn create_test_saves() -> (Vec<u8>, Vec<u8>) {
+    let raw_input_save = vec![0u8; SAVE_SLOT_SIZE[1] * MAX_LOOP_COUNT[1]];
+    let mut raw_output_save = raw_input_save.clone();
+    raw_output_save[0x10] = 0x49;
+    (raw_input_save, raw_output_save)
+  }
+
+  #[test]
+  fn file_on_disk_is_checked() {
+    let (raw_input_save, raw_output_save) = create_test_saves();
+    let output_file_bytes = [b"header".as_slice(), &raw_output_save].concat();
+    let undo_record = create_undo_record(&raw_input_save, &raw_output_save, &output_file_bytes, 1);
+
+    let mut raw_save_file = raw_output_save.clone();
+    assert!(revert_save(&mut raw_save_file, &[b"HEADER".as_slice(), &raw_output_save].concat(), &undo_record).is_err());
+    assert_eq!(revert_save(&mut raw_save_file, &output_file_bytes, &undo_record), Ok(()));
+    assert_eq!(raw_save_file, raw_input_save);
+  }
+
+  #[test]
+  fn version_1_undo_file_without_file_crc32() {
+    let (raw_input_save, raw_output_save) = create_test_saves();
+    let undo_record = create_undo_record(&raw_input_save, &raw_output_save, &raw_output_save, 1)
+      .replace("version 2", "version 1")
+      .lines().filter(|line| !line.starts_with("output_file_crc32")).collect::<Vec<&str>>().join("\n");
+
+    let mut raw_save_file = raw_output_save.clone();
+    assert_eq!(revert_save(&mut raw_save_file, b"not checked", &undo_record), Ok(()));
+    assert_eq!(raw_save_file, raw_input_save);
+    // A version 2 undo file must have it.
+    let mut raw_save_file = raw_output_save.clone();
+    assert_eq!(revert_save(&mut raw_save_file, &raw_output_save, &undo_record.replace("version 1", "version 2")), Err(String::from("The undo file is incomplete!")));
+  }
+}